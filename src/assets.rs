@@ -1,3 +1,7 @@
+use crate::data::WelcomeCard;
+
+use image::GenericImageView;
+
 use core::cmp;
 
 #[derive(Clone)]
@@ -16,20 +20,173 @@ impl Img {
     }
 }
 
+//Text, drawn with the same font of chain.
+struct Run {
+    font: usize,
+    text: String,
+    //Offset from start of text
+    x: f32,
+    width: f32,
+}
+
 #[derive(Clone)]
 pub struct Font {
-    pub welcome: rusttype::Font<'static>
+    pub welcome: rusttype::Font<'static>,
+    ///Used for characters, which are missing in `welcome`, e.g. symbols.
+    pub fallback: rusttype::Font<'static>,
 }
 
 impl Font {
     const WELCOME: &'static [u8] = include_bytes!("../assets/font/welcome.ttf");
+    const FALLBACK: &'static [u8] = include_bytes!("../assets/font/fallback.ttf");
 
     #[inline]
     pub fn new() -> Self {
         Self {
             welcome: rusttype::Font::try_from_bytes(Self::WELCOME).expect("Load welcome font"),
+            fallback: rusttype::Font::try_from_bytes(Self::FALLBACK).expect("Load fallback font"),
+        }
+    }
+
+    #[inline]
+    ///Returns fonts in order of preference.
+    fn chain(&self) -> [&rusttype::Font<'static>; 2] {
+        [&self.welcome, &self.fallback]
+    }
+
+    #[inline]
+    fn find(&self, ch: char) -> Option<usize> {
+        self.chain().iter().position(|font| font.glyph(ch).id().0 != 0)
+    }
+
+    ///Removes characters, which cannot be drawn by any font.
+    pub fn sanitize(&self, text: &str) -> String {
+        let text: String = text.chars().filter(|ch| self.find(*ch).is_some()).collect();
+        text.trim().to_owned()
+    }
+
+    //Splits text into runs of the same font, positioned by advance widths and kerning.
+    fn runs(&self, text: &str, scale: rusttype::Scale) -> Vec<Run> {
+        let chain = self.chain();
+        let mut runs: Vec<Run> = Vec::new();
+        let mut x = 0.0;
+        let mut previous = None;
+
+        for ch in text.chars() {
+            let idx = match self.find(ch) {
+                Some(idx) => idx,
+                None => continue,
+            };
+            let font = chain[idx];
+            let glyph = font.glyph(ch);
+            let id = glyph.id();
+
+            //Kerning is only defined within the same font
+            if let Some((previous_idx, previous_id)) = previous {
+                if previous_idx == idx {
+                    x += font.pair_kerning(scale, previous_id, id);
+                }
+            }
+            let advance = glyph.scaled(scale).h_metrics().advance_width;
+
+            match runs.last_mut() {
+                Some(run) if run.font == idx => {
+                    run.text.push(ch);
+                    run.width = x + advance - run.x;
+                },
+                _ => runs.push(Run {
+                    font: idx,
+                    text: ch.to_string(),
+                    x,
+                    width: advance,
+                }),
+            }
+
+            x += advance;
+            previous = Some((idx, id));
+        }
+
+        runs
+    }
+
+    ///Returns width of text in pixels.
+    pub fn measure(&self, text: &str, scale: rusttype::Scale) -> f32 {
+        self.runs(text, scale).last().map(|run| run.x + run.width).unwrap_or(0.0)
+    }
+
+    ///Shrinks font down to `min_size` to fit text into `width`, cutting text if it is still too long.
+    pub fn fit(&self, text: &str, size: f32, min_size: f32, width: f32) -> (String, rusttype::Scale) {
+        const ELLIPSIS: char = '…';
+
+        let text_width = self.measure(text, rusttype::Scale::uniform(size));
+        if text_width <= width {
+            return (text.to_owned(), rusttype::Scale::uniform(size));
         }
+
+        //Width is proportional to size, except for rounding
+        let mut size = (size * width / text_width).floor().max(min_size);
+        while size > min_size && self.measure(text, rusttype::Scale::uniform(size)) > width {
+            size = (size - 1.0).max(min_size);
+        }
+        let scale = rusttype::Scale::uniform(size);
+
+        let mut text = text.to_owned();
+        if self.measure(&text, scale) > width {
+            while !text.is_empty() {
+                text.pop();
+                let cut = format!("{}{}", text.trim_end(), ELLIPSIS);
+                if self.measure(&cut, scale) <= width {
+                    text = cut;
+                    break;
+                }
+            }
+        }
+
+        (text, scale)
     }
+
+    ///Draws text, using fallback fonts for missing characters.
+    ///
+    ///`y` is top of line in welcome font, other fonts share its baseline.
+    pub fn draw(&self, img: &mut image::DynamicImage, color: image::Rgba<u8>, x: u32, y: u32, scale: rusttype::Scale, text: &str) {
+        let chain = self.chain();
+        let baseline = y as f32 + self.welcome.v_metrics(scale).ascent;
+
+        for run in self.runs(text, scale) {
+            let font = chain[run.font];
+            let top = (baseline - font.v_metrics(scale).ascent).max(0.0);
+            imageproc::drawing::draw_text_mut(img, color, x + run.x.round() as u32, top.round() as u32, scale, font, &run.text);
+        }
+    }
+}
+
+///Crops avatar into circle of `size` diameter, surrounded by border.
+fn circle_avatar(avatar: &image::DynamicImage, size: u32, border: u32, color: image::Rgba<u8>) -> image::RgbaImage {
+    let inner = size.saturating_sub(border * 2).max(1);
+    let avatar = image::imageops::resize(avatar, inner, inner, image::imageops::FilterType::Triangle);
+
+    let center = size as f32 / 2.0;
+    let outer_radius = center;
+    let inner_radius = inner as f32 / 2.0;
+
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        let distance = ((x as f32 + 0.5 - center).powi(2) + (y as f32 + 0.5 - center).powi(2)).sqrt();
+        //Edges are anti-aliased by covered fraction of pixel
+        let coverage = |radius: f32| (radius - distance + 0.5).max(0.0).min(1.0);
+
+        let inner_coverage = coverage(inner_radius);
+        let mut pixel = match inner_coverage > 0.0 {
+            true => {
+                let [red, green, blue, alpha] = avatar.get_pixel(cmp::min(x.saturating_sub(border), inner - 1), cmp::min(y.saturating_sub(border), inner - 1)).0;
+                //Blend with border, so that there is no gap between them
+                let blend = |top: u8, bottom: u8| (top as f32 * inner_coverage + bottom as f32 * (1.0 - inner_coverage)) as u8;
+                image::Rgba([blend(red, color[0]), blend(green, color[1]), blend(blue, color[2]), blend(alpha, color[3])])
+            },
+            false => color,
+        };
+        pixel[3] = (pixel[3] as f32 * coverage(outer_radius)) as u8;
+        pixel
+    })
 }
 
 #[derive(Clone)]
@@ -47,63 +204,96 @@ impl Assets {
         }
     }
 
-    pub fn gen_welcome(&self, name: &str) -> Option<image::DynamicImage> {
-        const LETTER_SIZE: f32 = 28.0;
-        const TEXT_BOX_X_MAX: u32 = 900;
-        const TEXT_BOX_X: u32 = 460;
-        //Modify Y position accordingly to font
-        const TEXT_BOX_Y: u32 = 364;
-
-        //~18px per letter so we need to crop a bit
-        //Discord allows up to 32 letters for nickname
-        //but smaller font doesn't look so good
-        //To fit 32 we'd need ~14px letter
-        //TODO: it works well for latin letters
-        //but current font may not support all of UTF-8
-        const LETTER_PX_X: u32 = 18;
-
-        const TEXT_BOX_X_SIZE: u32 = TEXT_BOX_X_MAX - TEXT_BOX_X;
-        const MAX_LETTER_NUM: usize = TEXT_BOX_X_SIZE as usize / LETTER_PX_X as usize;
-
-        let name = name.trim_matches(|ch| self.font.welcome.glyph(ch).id().0 == 0);
-        let name = name.replace(|ch| self.font.welcome.glyph(ch).id().0 == 0, " ");
-        let name = name.as_str();
-        if name.is_empty() {
+    ///Draws text and avatar onto welcome card, using default background unless server has its own.
+    ///
+    ///Avatar is encoded image, which is skipped if it cannot be decoded.
+    pub fn gen_welcome(&self, text: &str, card: &WelcomeCard, background: Option<&image::DynamicImage>, avatar: Option<&[u8]>) -> Option<image::DynamicImage> {
+        //Text may be shrunk down to half of its size, before it is cut
+        const MIN_SHRINK: f32 = 0.5;
+
+        let mut img = background.unwrap_or(&self.img.welcome).clone();
+        //Layout may be configured for larger background
+        let text_box_x = cmp::min(u32::from(card.x), img.width().saturating_sub(1));
+        let text_box_y = cmp::min(u32::from(card.y), img.height().saturating_sub(1));
+        let text_box_x_size = cmp::min(u32::from(card.width), img.width() - text_box_x);
+
+        let text = self.font.sanitize(text);
+        if text.is_empty() {
             return None;
         }
 
-        let mut name_len = 0;
-        //Japanese/Chinese full width may take 4 bytes
-        //while actual length of character would take space
-        //of two latin characters
-        for ch in name.chars() {
-            name_len += cmp::min(ch.len_utf8(), 2);
+        let size = card.font_size as f32;
+        let (text, scale) = self.font.fit(&text, size, size * MIN_SHRINK, text_box_x_size as f32);
+        let text_len_px = cmp::min(text_box_x_size, self.font.measure(&text, scale).ceil() as u32);
+        let shift_x = (text_box_x_size - text_len_px) / 2;
+        //Shrunk text stays in the middle of line
+        let shift_y = ((size - scale.y) / 2.0) as u32;
+        let [red, green, blue] = card.color;
+
+        self.font.draw(&mut img, image::Rgba([red, green, blue, 255]),
+                       text_box_x + shift_x, text_box_y + shift_y,
+                       scale, &text);
+
+        if let Some(avatar) = avatar.filter(|_| card.avatar_size > 0).and_then(|avatar| image::load_from_memory(avatar).ok()) {
+            let size = u32::from(card.avatar_size);
+            let border = cmp::max(size / 32, 2);
+            let avatar = circle_avatar(&avatar, size, border, image::Rgba([red, green, blue, 255]));
+            image::imageops::overlay(&mut img, &avatar, u32::from(card.avatar_x), u32::from(card.avatar_y));
         }
 
-        let name = if name_len > MAX_LETTER_NUM {
-            //Safeguard against hitting middle of Unicode character
-            let mut max = MAX_LETTER_NUM;
-            loop {
-                if let Some(result) = name.get(..max) {
-                    break result
-                } else {
-                    max -= 1;
-                }
-            }
-        } else {
-            name
+        Some(img)
+    }
+
+    pub fn gen_goodbye(&self, name: &str, avatar: Option<&[u8]>) -> image::DynamicImage {
+        const WIDTH: u32 = 600;
+        const HEIGHT: u32 = 150;
+        const AVATAR_SIZE: u32 = 110;
+        const BACKGROUND: image::Rgba<u8> = image::Rgba([54, 57, 63, 255]);
+        const FOREGROUND: image::Rgba<u8> = image::Rgba([185, 187, 190, 255]);
+
+        let mut img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND));
+        let text_x = match avatar.and_then(|avatar| image::load_from_memory(avatar).ok()) {
+            Some(avatar) => {
+                //Faded avatar of the one, who is gone
+                let avatar = circle_avatar(&avatar.grayscale(), AVATAR_SIZE, 3, FOREGROUND);
+                image::imageops::overlay(&mut img, &avatar, 20, (HEIGHT - AVATAR_SIZE) / 2);
+                AVATAR_SIZE + 40
+            },
+            None => 20,
         };
 
-        let name_len_px = cmp::min(TEXT_BOX_X_SIZE, name_len as u32 * LETTER_PX_X);
-        let shift_x = (TEXT_BOX_X_SIZE - name_len_px) / 2;
-        let scale = rusttype::Scale { x: LETTER_SIZE * 1.25, y: LETTER_SIZE * 1.25 };
+        imageproc::drawing::draw_text_mut(&mut img, FOREGROUND, text_x, 20,
+                                          rusttype::Scale::uniform(48.0), &self.font.welcome, "Goodbye!");
+        let name = self.font.sanitize(name);
+        let (name, scale) = self.font.fit(&name, 32.0, 16.0, (WIDTH - text_x - 20) as f32);
+        self.font.draw(&mut img, FOREGROUND, text_x, 90, scale, &name);
 
-        let mut img = self.img.welcome.clone();
-        imageproc::drawing::draw_text_mut(&mut img, image::Rgba([238, 183, 149, 255]),
-                                          TEXT_BOX_X + shift_x, TEXT_BOX_Y,
-                                          scale, &self.font.welcome, name);
+        img
+    }
 
-        Some(img)
+    pub fn gen_level_up(&self, name: &str, level: u8) -> image::DynamicImage {
+        const WIDTH: u32 = 600;
+        const HEIGHT: u32 = 150;
+        const MAX_NAME_LEN: usize = 24;
+        const BACKGROUND: image::Rgba<u8> = image::Rgba([54, 57, 63, 255]);
+        const FOREGROUND: image::Rgba<u8> = image::Rgba([238, 183, 149, 255]);
+
+        let name = self.font.sanitize(name);
+        let name = match name.char_indices().nth(MAX_NAME_LEN) {
+            Some((idx, _)) => &name[..idx],
+            None => name.as_str(),
+        };
+        let level = level.to_string();
+
+        let mut img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(WIDTH, HEIGHT, BACKGROUND));
+        imageproc::drawing::draw_text_mut(&mut img, FOREGROUND, 20, 20,
+                                          rusttype::Scale::uniform(48.0), &self.font.welcome, "Level up!");
+        self.font.draw(&mut img, FOREGROUND, 20, 90,
+                       rusttype::Scale::uniform(32.0), name);
+        imageproc::drawing::draw_text_mut(&mut img, FOREGROUND, WIDTH - 150, 25,
+                                          rusttype::Scale::uniform(96.0), &self.font.welcome, &level);
+
+        img
     }
 }
 
@@ -116,7 +306,7 @@ mod tests {
         let text = "おしざーおしざー";
 
         let assets = Assets::new();
-        let welcome = assets.gen_welcome(text).expect("Welcome img");
+        let welcome = assets.gen_welcome(text, &WelcomeCard::new(), None, None).expect("Welcome img");
         let mut image_buffer = Vec::new();
         welcome.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
         std::fs::write("test1.png", image_buffer.as_slice()).expect("Write file");
@@ -127,7 +317,7 @@ mod tests {
         let text = "Chitanda Eru";
 
         let assets = Assets::new();
-        let welcome = assets.gen_welcome(text).expect("Welcome img");
+        let welcome = assets.gen_welcome(text, &WelcomeCard::new(), None, None).expect("Welcome img");
         let mut image_buffer = Vec::new();
         welcome.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
         std::fs::write("test2.png", image_buffer.as_slice()).expect("Write file");
@@ -138,10 +328,109 @@ mod tests {
         let text = "✦ღGlitter Gal Lilacღ✦";
 
         let assets = Assets::new();
-        let welcome = assets.gen_welcome(text).expect("Welcome img");
+        let welcome = assets.gen_welcome(text, &WelcomeCard::new(), None, None).expect("Welcome img");
         let mut image_buffer = Vec::new();
         welcome.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
         std::fs::write("test3.png", image_buffer.as_slice()).expect("Write file");
     }
 
+    #[test]
+    fn should_draw_avatar_in_circle() {
+        const RED: image::Rgba<u8> = image::Rgba([255, 0, 0, 255]);
+
+        let avatar = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(64, 64, RED));
+        let mut avatar_bytes = Vec::new();
+        avatar.write_to(&mut avatar_bytes, image::ImageOutputFormat::Png).expect("Write avatar");
+
+        let assets = Assets::new();
+        let card = WelcomeCard::new();
+        let plain = assets.gen_welcome("Chitanda Eru", &card, None, None).expect("Welcome img");
+        let welcome = assets.gen_welcome("Chitanda Eru", &card, None, Some(&avatar_bytes)).expect("Welcome img");
+
+        let (x, y, size) = (card.avatar_x as u32, card.avatar_y as u32, card.avatar_size as u32);
+        assert_eq!(welcome.get_pixel(x + size / 2, y + size / 2), RED);
+        //Corners of avatar's box are outside of circle
+        assert_eq!(welcome.get_pixel(x, y), plain.get_pixel(x, y));
+        assert_eq!(welcome.get_pixel(x + size - 1, y + size - 1), plain.get_pixel(x + size - 1, y + size - 1));
+        //Border has colour of text
+        let [red, green, blue] = card.color;
+        assert_eq!(welcome.get_pixel(x + size / 2, y + 1), image::Rgba([red, green, blue, 255]));
+
+        let mut image_buffer = Vec::new();
+        welcome.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
+        std::fs::write("test5.png", image_buffer.as_slice()).expect("Write file");
+    }
+
+    #[test]
+    fn should_skip_broken_avatar() {
+        let assets = Assets::new();
+        let card = WelcomeCard::new();
+        let plain = assets.gen_welcome("Chitanda Eru", &card, None, None).expect("Welcome img");
+        let welcome = assets.gen_welcome("Chitanda Eru", &card, None, Some(b"not an image")).expect("Welcome img");
+
+        assert_eq!(plain.to_rgba8().into_raw(), welcome.to_rgba8().into_raw());
+    }
+
+    #[test]
+    fn should_fall_back_to_other_font() {
+        let font = Font::new();
+
+        assert_eq!(font.sanitize(" ✦ღGlitter\u{7}ღ✦ "), "✦ღGlitterღ✦");
+        assert_eq!(font.find('G'), Some(0));
+        assert_eq!(font.find('お'), Some(0));
+        assert_eq!(font.find('✦'), Some(1));
+
+        let runs = font.runs("✦Gal✦", rusttype::Scale::uniform(32.0));
+        let runs: Vec<_> = runs.iter().map(|run| (run.font, run.text.as_str())).collect();
+        assert_eq!(runs, [(1, "✦"), (0, "Gal"), (1, "✦")]);
+    }
+
+    #[test]
+    fn should_fit_text_into_box() {
+        let font = Font::new();
+
+        let (text, scale) = font.fit("Chitanda", 35.0, 17.5, 440.0);
+        assert_eq!(text, "Chitanda");
+        assert_eq!(scale.y, 35.0);
+
+        let long = "Chitanda Eru Chitanda Eru Chitanda";
+        let (text, scale) = font.fit(long, 35.0, 17.5, 440.0);
+        assert_eq!(text, long);
+        assert!(scale.y < 35.0 && scale.y >= 17.5);
+        assert!(font.measure(&text, scale) <= 440.0);
+
+        let (text, scale) = font.fit(&long.repeat(4), 35.0, 17.5, 440.0);
+        assert_eq!(scale.y, 17.5);
+        assert!(text.ends_with('…'));
+        assert!(font.measure(&text, scale) <= 440.0);
+    }
+
+    #[test]
+    fn verify_goodbye() {
+        let avatar = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(64, 64, image::Rgba([255, 0, 0, 255])));
+        let mut avatar_bytes = Vec::new();
+        avatar.write_to(&mut avatar_bytes, image::ImageOutputFormat::Png).expect("Write avatar");
+
+        let assets = Assets::new();
+        let plain = assets.gen_goodbye("✦ღGlitter Gal Lilacღ✦", None);
+        let goodbye = assets.gen_goodbye("✦ღGlitter Gal Lilacღ✦", Some(&avatar_bytes));
+        assert_eq!(plain.dimensions(), goodbye.dimensions());
+
+        //Avatar is drawn in grayscale
+        let image::Rgba([red, green, blue, _]) = goodbye.get_pixel(75, 75);
+        assert!(red == green && green == blue);
+
+        let mut image_buffer = Vec::new();
+        goodbye.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
+        std::fs::write("test6.png", image_buffer.as_slice()).expect("Write file");
+    }
+
+    #[test]
+    fn verify_level_up() {
+        let assets = Assets::new();
+        let level_up = assets.gen_level_up("✦ღGlitter Gal Lilacღ✦", 42);
+        let mut image_buffer = Vec::new();
+        level_up.write_to(&mut image_buffer, image::ImageOutputFormat::Png).expect("Write buffer");
+        std::fs::write("test4.png", image_buffer.as_slice()).expect("Write file");
+    }
 }
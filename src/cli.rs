@@ -21,15 +21,41 @@ impl FromStr for Token {
     }
 }
 
+pub type PrefixStr = str_buf::StrBuf<8>;
+
+#[derive(Clone, Copy, Debug)]
+#[repr(transparent)]
+pub struct Prefix(pub PrefixStr);
+
+impl FromStr for Prefix {
+    type Err = ();
+
+    #[inline]
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.is_empty() || text.len() > PrefixStr::capacity() || text.contains(char::is_whitespace) {
+            Err(())
+        } else {
+            Ok(Prefix(PrefixStr::from_str(text)))
+        }
+    }
+}
+
+impl Default for Prefix {
+    #[inline]
+    fn default() -> Self {
+        Prefix(PrefixStr::from_str("."))
+    }
+}
+
 #[derive(Args, Debug, Clone)]
 ///Find files utility
 pub struct Cli {
     #[arg(long, short, required)]
     ///Discord token to use. Must be a string of 59 characters.
     pub token: Token,
-    #[arg(long, short, default_value = "'.'")]
-    ///Command prefix. Default is '.'.
-    pub prefix: char,
+    #[arg(long, short, default_value = "Prefix::default()")]
+    ///Default command prefix, up to 8 characters. Default is '.'.
+    pub prefix: Prefix,
 }
 
 impl Cli {
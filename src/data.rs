@@ -1,85 +1,1147 @@
 //! Data types and serialization
 
+use crate::lang::Lang;
+use crate::utils::truncate;
+
 use core::time;
 
-pub trait Serialize {
-    const SIZE: usize;
-    //Generally should be static array;
-    type Output: AsRef<[u8]>;
+pub trait Serialize {
+    const SIZE: usize;
+    //Generally should be static array;
+    type Output: AsRef<[u8]>;
+
+    fn serialize(&self) -> Self::Output;
+}
+
+pub trait Deserialize: Serialize {
+    fn deserialize(ser: &Self::Output) -> Self;
+}
+
+//Sequential little-endian reader over fixed size record.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    const fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+        }
+    }
+
+    #[inline]
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let mut result = [0u8; N];
+        result.copy_from_slice(&self.data[self.pos..self.pos + N]);
+        self.pos += N;
+        result
+    }
+
+    #[inline]
+    fn u8(&mut self) -> u8 {
+        self.bytes::<1>()[0]
+    }
+
+    #[inline]
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.bytes())
+    }
+
+    #[inline]
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.bytes())
+    }
+}
+
+//Sequential little-endian writer into fixed size record.
+struct Writer<'a> {
+    data: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    #[inline]
+    fn new(data: &'a mut [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+        }
+    }
+
+    #[inline]
+    fn bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        self.data[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+        self
+    }
+
+    #[inline]
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.bytes(&[value])
+    }
+
+    #[inline]
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    fn u64s(&mut self, values: &[u64]) -> &mut Self {
+        for value in values {
+            self.u64(*value);
+        }
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Where to announce level up.
+pub enum LevelUpDest {
+    ///Spam channel, if set.
+    Spam,
+    ///Channel, where level up happened.
+    ///
+    ///Fallbacks to spam channel if there is no such channel (e.g. voice).
+    Channel,
+    ///Direct message.
+    Dm,
+    Off,
+}
+
+impl LevelUpDest {
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => LevelUpDest::Channel,
+            2 => LevelUpDest::Dm,
+            3 => LevelUpDest::Off,
+            _ => LevelUpDest::Spam,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            LevelUpDest::Spam => 0,
+            LevelUpDest::Channel => 1,
+            LevelUpDest::Dm => 2,
+            LevelUpDest::Off => 3,
+        }
+    }
+}
+
+impl Default for LevelUpDest {
+    #[inline]
+    fn default() -> Self {
+        LevelUpDest::Spam
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Server {
+    pub welcome_ch: u64,
+    pub music_ch: u64,
+    pub dev_ch: u64,
+    pub spam_ch: u64,
+    //Exp multiplier in percents, 0 means no event is scheduled.
+    pub exp_event_rate: u16,
+    //Seconds since epoch
+    pub exp_event_start: u64,
+    //Seconds since epoch
+    pub exp_event_end: u64,
+    pub level_up_dest: LevelUpDest,
+    pub level_up_img: bool,
+    //Explicitly configured moderator roles, 0 means empty slot.
+    pub mod_roles: [u64; Server::MAX_MOD_ROLES],
+    //Command prefix, padded with zeroes. Empty means default one.
+    pub prefix: [u8; Server::MAX_PREFIX],
+    pub lang: Lang,
+    //Role, which is given to muted users
+    pub mute_role: u64,
+    pub modlog_ch: u64,
+    pub goodbye_ch: u64,
+    pub goodbye_img: bool,
+    //Seconds after leave, when member's server data is removed, 0 means never.
+    pub leave_cleanup: u64,
+    //Whether rate limits apply to moderators too.
+    pub limit_mods: bool,
+}
+
+impl Server {
+    pub const MAX_MOD_ROLES: usize = 8;
+    pub const MAX_PREFIX: usize = 8;
+
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        Self {
+            welcome_ch: data.u64(),
+            music_ch: data.u64(),
+            dev_ch: data.u64(),
+            spam_ch: data.u64(),
+            exp_event_rate: data.u16(),
+            exp_event_start: data.u64(),
+            exp_event_end: data.u64(),
+            level_up_dest: LevelUpDest::from_u8(data.u8()),
+            level_up_img: data.u8() != 0,
+            mod_roles: {
+                let mut mod_roles = [0; Self::MAX_MOD_ROLES];
+                for role in mod_roles.iter_mut() {
+                    *role = data.u64();
+                }
+                mod_roles
+            },
+            prefix: data.bytes(),
+            lang: Lang::from_u8(data.u8()),
+            mute_role: data.u64(),
+            modlog_ch: data.u64(),
+            goodbye_ch: data.u64(),
+            goodbye_img: data.u8() != 0,
+            leave_cleanup: data.u64(),
+            limit_mods: data.u8() != 0,
+        }
+    }
+
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u64(self.welcome_ch)
+                                .u64(self.music_ch)
+                                .u64(self.dev_ch)
+                                .u64(self.spam_ch)
+                                .u16(self.exp_event_rate)
+                                .u64(self.exp_event_start)
+                                .u64(self.exp_event_end)
+                                .u8(self.level_up_dest.to_u8())
+                                .u8(self.level_up_img as u8)
+                                .u64s(&self.mod_roles)
+                                .bytes(&self.prefix)
+                                .u8(self.lang.to_u8())
+                                .u64(self.mute_role)
+                                .u64(self.modlog_ch)
+                                .u64(self.goodbye_ch)
+                                .u8(self.goodbye_img as u8)
+                                .u64(self.leave_cleanup)
+                                .u8(self.limit_mods as u8);
+
+        result
+    }
+
+    ///Returns custom command prefix, if any.
+    pub fn prefix(&self) -> Option<&str> {
+        let len = self.prefix.iter().position(|byte| *byte == 0).unwrap_or(self.prefix.len());
+        match core::str::from_utf8(&self.prefix[..len]) {
+            Ok("") | Err(_) => None,
+            Ok(prefix) => Some(prefix),
+        }
+    }
+
+    ///Sets custom command prefix, returning `false` if it doesn't fit.
+    ///
+    ///Empty prefix resets it to default.
+    pub fn set_prefix(&mut self, prefix: &str) -> bool {
+        if prefix.len() > self.prefix.len() {
+            return false;
+        }
+
+        self.prefix = [0; Self::MAX_PREFIX];
+        self.prefix[..prefix.len()].copy_from_slice(prefix.as_bytes());
+        true
+    }
+
+    ///Returns exp multiplier in percents, that is active at the moment `now` (since epoch).
+    pub fn exp_event_rate(&self, now: time::Duration) -> u16 {
+        let now = now.as_secs();
+        if self.exp_event_rate != 0 && self.exp_event_start <= now && now < self.exp_event_end {
+            self.exp_event_rate
+        } else {
+            100
+        }
+    }
+}
+
+impl Serialize for Server {
+    const SIZE: usize = 159;
+    type Output = [u8; 159];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Server {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug)]
+pub struct Channel {
+    //Exp weight in percents.
+    pub exp_weight: u16,
+    ///Whether channel is locked by lockdown.
+    pub is_lockdown: bool,
+}
+
+impl Channel {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            exp_weight: 100,
+            is_lockdown: false,
+        }
+    }
+
+    #[inline]
+    pub const fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        Self {
+            exp_weight: u16::from_le_bytes([data[0], data[1]]),
+            is_lockdown: data[2] != 0,
+        }
+    }
+
+    #[inline]
+    pub const fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let exp_weight = self.exp_weight.to_le_bytes();
+        [exp_weight[0], exp_weight[1], self.is_lockdown as u8]
+    }
+}
+
+impl Serialize for Channel {
+    const SIZE: usize = 3;
+    type Output = [u8; 3];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Channel {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+impl Default for Channel {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Target of permission rule.
+pub enum PermissionTarget {
+    Role,
+    User,
+    Channel,
+}
+
+#[derive(Debug)]
+///Rule to allow or deny command.
+pub struct Permission {
+    pub target: PermissionTarget,
+    pub allow: bool,
+}
+
+impl Permission {
+    #[inline]
+    pub const fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        Self {
+            target: match data[0] {
+                1 => PermissionTarget::User,
+                2 => PermissionTarget::Channel,
+                _ => PermissionTarget::Role,
+            },
+            allow: data[1] != 0,
+        }
+    }
+
+    #[inline]
+    pub const fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let target = match self.target {
+            PermissionTarget::Role => 0,
+            PermissionTarget::User => 1,
+            PermissionTarget::Channel => 2,
+        };
+
+        [target, self.allow as u8]
+    }
+}
+
+impl Serialize for Permission {
+    const SIZE: usize = 2;
+    type Output = [u8; 2];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Permission {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+impl Default for Permission {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            target: PermissionTarget::Role,
+            allow: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+///Kind of server's template.
+pub enum TemplateKind {
+    LevelUp = 1,
+    ///Message, posted with welcome card.
+    Welcome = 2,
+    ///Text, drawn on welcome card.
+    WelcomeCard = 3,
+    Goodbye = 4,
+    ///Private message to new member.
+    WelcomeDm = 5,
+}
+
+#[derive(Debug, Default)]
+///Server's text template
+pub struct Template {
+    pub text: String,
+}
+
+impl Template {
+    //Length prefix takes 2 bytes
+    pub const MAX_LEN: usize = <Self as Serialize>::SIZE - 2;
+
+    ///Creates new template, truncating text to fit `MAX_LEN`
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: truncate(text, Self::MAX_LEN).to_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let len = core::cmp::min(u16::from_le_bytes([data[0], data[1]]) as usize, Self::MAX_LEN);
+
+        Self {
+            text: String::from_utf8_lossy(&data[2..2 + len]).into_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let text = Self::new(&self.text).text;
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u16(text.len() as u16)
+                                .bytes(text.as_bytes());
+
+        result
+    }
+
+    #[inline]
+    ///Returns text, if template is set.
+    pub fn get(&self) -> Option<&str> {
+        match self.text.is_empty() {
+            true => None,
+            false => Some(self.text.as_str()),
+        }
+    }
+}
+
+impl Serialize for Template {
+    const SIZE: usize = 1024;
+    type Output = [u8; 1024];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Template {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Moderation action.
+pub enum CaseAction {
+    Warn,
+    Kick,
+    Ban,
+    Unban,
+    Mute,
+    Unmute,
+}
+
+impl CaseAction {
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => CaseAction::Kick,
+            2 => CaseAction::Ban,
+            3 => CaseAction::Unban,
+            4 => CaseAction::Mute,
+            5 => CaseAction::Unmute,
+            _ => CaseAction::Warn,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            CaseAction::Warn => 0,
+            CaseAction::Kick => 1,
+            CaseAction::Ban => 2,
+            CaseAction::Unban => 3,
+            CaseAction::Mute => 4,
+            CaseAction::Unmute => 5,
+        }
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            CaseAction::Warn => "warn",
+            CaseAction::Kick => "kick",
+            CaseAction::Ban => "ban",
+            CaseAction::Unban => "unban",
+            CaseAction::Mute => "mute",
+            CaseAction::Unmute => "unmute",
+        }
+    }
+}
+
+impl Default for CaseAction {
+    #[inline]
+    fn default() -> Self {
+        CaseAction::Warn
+    }
+}
+
+#[derive(Debug, Default)]
+///Record of moderation action.
+pub struct Case {
+    pub action: CaseAction,
+    pub target: u64,
+    pub moderator: u64,
+    //Seconds since epoch
+    pub time: u64,
+    //Seconds, 0 means permanent
+    pub duration: u64,
+    pub reason: String,
+}
+
+impl Case {
+    //Fixed fields take 35 bytes
+    pub const MAX_REASON: usize = <Self as Serialize>::SIZE - 35;
+
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        let action = CaseAction::from_u8(data.u8());
+        let target = data.u64();
+        let moderator = data.u64();
+        let time = data.u64();
+        let duration = data.u64();
+        let len = core::cmp::min(data.u16() as usize, Self::MAX_REASON);
+        let reason = data.bytes::<{ Case::MAX_REASON }>();
+
+        Self {
+            action,
+            target,
+            moderator,
+            time,
+            duration,
+            reason: String::from_utf8_lossy(&reason[..len]).into_owned(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let reason = truncate(&self.reason, Self::MAX_REASON);
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u8(self.action.to_u8())
+                                .u64(self.target)
+                                .u64(self.moderator)
+                                .u64(self.time)
+                                .u64(self.duration)
+                                .u16(reason.len() as u16)
+                                .bytes(reason.as_bytes());
+
+        result
+    }
+}
+
+impl Serialize for Case {
+    const SIZE: usize = 547;
+    type Output = [u8; 547];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Case {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug, Default)]
+///Pending end of temporary ban or mute.
+pub struct Expiry {
+    ///Either `Ban` or `Mute`.
+    pub action: CaseAction,
+    //Mute role at the moment of mute
+    pub role: u64,
+}
+
+impl Expiry {
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        Self {
+            action: CaseAction::from_u8(data.u8()),
+            role: data.u64(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u8(self.action.to_u8())
+                                .u64(self.role);
+
+        result
+    }
+}
+
+impl Serialize for Expiry {
+    const SIZE: usize = 9;
+    type Output = [u8; 9];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Expiry {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug, Default)]
+///Member, who left server, pending removal of their server data.
+pub struct Departure {
+    //Seconds since epoch
+    pub time: u64,
+}
+
+impl Departure {
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        Self {
+            time: Reader::new(data).u64(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        self.time.to_le_bytes()
+    }
+}
+
+impl Serialize for Departure {
+    const SIZE: usize = 8;
+    type Output = [u8; 8];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Departure {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Auto-moderation filter.
+pub enum Filter {
+    ///Blocked words and patterns.
+    Words,
+    Invites,
+    ///Links to anything, but discord.
+    Links,
+    ///Percentage of uppercase letters.
+    Caps,
+    Mentions,
+    Emoji,
+    ///Repeated messages.
+    Spam,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 7] = [Filter::Words, Filter::Invites, Filter::Links, Filter::Caps, Filter::Mentions, Filter::Emoji, Filter::Spam];
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Filter::Words => "words",
+            Filter::Invites => "invites",
+            Filter::Links => "links",
+            Filter::Caps => "caps",
+            Filter::Mentions => "mentions",
+            Filter::Emoji => "emoji",
+            Filter::Spam => "spam",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|filter| filter.name().eq_ignore_ascii_case(name))
+    }
+
+    #[inline]
+    ///Returns limit, used when it is not configured.
+    ///
+    ///Filters without limit return 0.
+    pub const fn default_limit(self) -> u16 {
+        match self {
+            Filter::Words | Filter::Invites | Filter::Links => 0,
+            //Percents
+            Filter::Caps => 70,
+            Filter::Mentions => 5,
+            Filter::Emoji => 10,
+            //Number of the same messages in a row
+            Filter::Spam => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Action, taken when message is caught by filter.
+pub enum FilterAction {
+    Off,
+    Delete,
+    ///Deletes and warns.
+    Warn,
+    ///Deletes and mutes for configured duration.
+    Mute,
+}
+
+impl FilterAction {
+    pub const ALL: [FilterAction; 4] = [FilterAction::Off, FilterAction::Delete, FilterAction::Warn, FilterAction::Mute];
+
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => FilterAction::Delete,
+            2 => FilterAction::Warn,
+            3 => FilterAction::Mute,
+            _ => FilterAction::Off,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            FilterAction::Off => 0,
+            FilterAction::Delete => 1,
+            FilterAction::Warn => 2,
+            FilterAction::Mute => 3,
+        }
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            FilterAction::Off => "off",
+            FilterAction::Delete => "delete",
+            FilterAction::Warn => "warn",
+            FilterAction::Mute => "mute",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for FilterAction {
+    #[inline]
+    fn default() -> Self {
+        FilterAction::Off
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FilterRule {
+    pub action: FilterAction,
+    //0 means filter's default
+    pub limit: u16,
+}
+
+#[derive(Debug)]
+///Server's auto-moderation settings.
+pub struct AutoMod {
+    //Indexed by filter, extra slots are reserved for new filters.
+    pub rules: [FilterRule; AutoMod::MAX_RULES],
+    //Seconds
+    pub mute_duration: u64,
+    //Exempted channels and roles, 0 means empty slot.
+    pub exempt_channels: [u64; AutoMod::MAX_EXEMPT],
+    pub exempt_roles: [u64; AutoMod::MAX_EXEMPT],
+}
+
+impl AutoMod {
+    pub const MAX_RULES: usize = 8;
+    pub const MAX_EXEMPT: usize = 8;
+
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        let mut rules = [FilterRule::default(); Self::MAX_RULES];
+        for rule in rules.iter_mut() {
+            rule.action = FilterAction::from_u8(data.u8());
+            rule.limit = data.u16();
+        }
+        let mute_duration = data.u64();
+        let mut exempt_channels = [0; Self::MAX_EXEMPT];
+        for channel in exempt_channels.iter_mut() {
+            *channel = data.u64();
+        }
+        let mut exempt_roles = [0; Self::MAX_EXEMPT];
+        for role in exempt_roles.iter_mut() {
+            *role = data.u64();
+        }
+
+        Self {
+            rules,
+            mute_duration,
+            exempt_channels,
+            exempt_roles,
+        }
+    }
+
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        let mut writer = Writer::new(&mut result);
+        for rule in self.rules.iter() {
+            writer.u8(rule.action.to_u8()).u16(rule.limit);
+        }
+        writer.u64(self.mute_duration)
+              .u64s(&self.exempt_channels)
+              .u64s(&self.exempt_roles);
+
+        result
+    }
+
+    #[inline]
+    pub fn rule(&self, filter: Filter) -> FilterRule {
+        self.rules[filter as usize]
+    }
+
+    #[inline]
+    pub fn rule_mut(&mut self, filter: Filter) -> &mut FilterRule {
+        &mut self.rules[filter as usize]
+    }
+
+    #[inline]
+    ///Returns configured limit of filter or its default.
+    pub fn limit(&self, filter: Filter) -> u16 {
+        match self.rule(filter).limit {
+            0 => filter.default_limit(),
+            limit => limit,
+        }
+    }
+
+    ///Returns whether any filter is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.rules.iter().any(|rule| rule.action != FilterAction::Off)
+    }
+
+    ///Returns whether message in channel from member with roles is not subject to filters.
+    pub fn is_exempt(&self, channel: u64, roles: &[u64]) -> bool {
+        self.exempt_channels.contains(&channel) || roles.iter().any(|role| *role != 0 && self.exempt_roles.contains(role))
+    }
+}
+
+impl Default for AutoMod {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            rules: [FilterRule::default(); Self::MAX_RULES],
+            //10 minutes
+            mute_duration: 10 * 60,
+            exempt_channels: [0; Self::MAX_EXEMPT],
+            exempt_roles: [0; Self::MAX_EXEMPT],
+        }
+    }
+}
+
+impl Serialize for AutoMod {
+    const SIZE: usize = 160;
+    type Output = [u8; 160];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for AutoMod {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug, Default)]
+///Blocked word or regex pattern.
+pub struct BlockPattern {
+    pub is_regex: bool,
+    pub text: String,
+}
 
-    fn serialize(&self) -> Self::Output;
+impl BlockPattern {
+    //Flag and length prefix take 3 bytes
+    pub const MAX_LEN: usize = <Self as Serialize>::SIZE - 3;
+
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        let is_regex = data.u8() != 0;
+        let len = core::cmp::min(data.u16() as usize, Self::MAX_LEN);
+        let text = data.bytes::<{ BlockPattern::MAX_LEN }>();
+
+        Self {
+            is_regex,
+            text: String::from_utf8_lossy(&text[..len]).into_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let text = truncate(&self.text, Self::MAX_LEN);
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u8(self.is_regex as u8)
+                                .u16(text.len() as u16)
+                                .bytes(text.as_bytes());
+
+        result
+    }
 }
 
-pub trait Deserialize: Serialize {
-    fn deserialize(ser: &Self::Output) -> Self;
+impl Serialize for BlockPattern {
+    const SIZE: usize = 256;
+    type Output = [u8; 256];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for BlockPattern {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Action, applied to members joining during raid.
+pub enum RaidAction {
+    ///Only welcome is suppressed.
+    Nothing,
+    Kick,
+    ///Gives quarantine role.
+    Quarantine,
+}
+
+impl RaidAction {
+    pub const ALL: [RaidAction; 3] = [RaidAction::Nothing, RaidAction::Kick, RaidAction::Quarantine];
+
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => RaidAction::Kick,
+            2 => RaidAction::Quarantine,
+            _ => RaidAction::Nothing,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            RaidAction::Nothing => 0,
+            RaidAction::Kick => 1,
+            RaidAction::Quarantine => 2,
+        }
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            RaidAction::Nothing => "none",
+            RaidAction::Kick => "kick",
+            RaidAction::Quarantine => "quarantine",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for RaidAction {
+    #[inline]
+    fn default() -> Self {
+        RaidAction::Nothing
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+///Server's raid protection settings and state.
+pub struct Raid {
+    //Number of joins within period, that triggers raid mode, 0 means disabled.
+    pub joins: u16,
+    //Seconds
+    pub period: u16,
+    //Days, accounts younger than that trigger raid mode, 0 means disabled.
+    pub min_age: u16,
+    pub action: RaidAction,
+    pub quarantine_role: u64,
+    ///Whether raid mode is on, until lifted by moderators.
+    pub is_active: bool,
+}
+
+impl Raid {
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        Self {
+            joins: data.u16(),
+            period: data.u16(),
+            min_age: data.u16(),
+            action: RaidAction::from_u8(data.u8()),
+            quarantine_role: data.u64(),
+            is_active: data.u8() != 0,
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u16(self.joins)
+                                .u16(self.period)
+                                .u16(self.min_age)
+                                .u8(self.action.to_u8())
+                                .u64(self.quarantine_role)
+                                .u8(self.is_active as u8);
+
+        result
+    }
+}
+
+impl Serialize for Raid {
+    const SIZE: usize = 16;
+    type Output = [u8; 16];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Raid {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
 }
 
 #[derive(Debug, Default)]
-pub struct Server {
-    pub welcome_ch: u64,
-    pub music_ch: u64,
-    pub dev_ch: u64,
-    pub spam_ch: u64,
+///Channel's state before it was locked.
+pub struct Lock {
+    pub is_locked: bool,
+    //Whether @everyone had overwrite, otherwise it is removed on unlock.
+    pub had_overwrite: bool,
+    //Permissions bits of @everyone overwrite
+    pub allow: u64,
+    pub deny: u64,
 }
 
-impl Server {
+impl Lock {
     #[inline]
-    pub const fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
         Self {
-            welcome_ch: u64::from_le_bytes([data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7]]),
-            music_ch: u64::from_le_bytes([data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15]]),
-            dev_ch: u64::from_le_bytes([data[16], data[17], data[18], data[19], data[20], data[21], data[22], data[23]]),
-            spam_ch: u64::from_le_bytes([data[24], data[25], data[26], data[27], data[28], data[29], data[30], data[31]]),
+            is_locked: data.u8() != 0,
+            had_overwrite: data.u8() != 0,
+            allow: data.u64(),
+            deny: data.u64(),
         }
     }
 
     #[inline]
-    pub const fn to_bytes(&self) -> <Self as Serialize>::Output {
-        let welcome_ch = self.welcome_ch.to_le_bytes();
-        let music_ch = self.music_ch.to_le_bytes();
-        let dev_ch = self.dev_ch.to_le_bytes();
-        let spam_ch = self.spam_ch.to_le_bytes();
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
 
-        [
-            welcome_ch[0],
-            welcome_ch[1],
-            welcome_ch[2],
-            welcome_ch[3],
-            welcome_ch[4],
-            welcome_ch[5],
-            welcome_ch[6],
-            welcome_ch[7],
-            music_ch[0],
-            music_ch[1],
-            music_ch[2],
-            music_ch[3],
-            music_ch[4],
-            music_ch[5],
-            music_ch[6],
-            music_ch[7],
-            dev_ch[0],
-            dev_ch[1],
-            dev_ch[2],
-            dev_ch[3],
-            dev_ch[4],
-            dev_ch[5],
-            dev_ch[6],
-            dev_ch[7],
-            spam_ch[0],
-            spam_ch[1],
-            spam_ch[2],
-            spam_ch[3],
-            spam_ch[4],
-            spam_ch[5],
-            spam_ch[6],
-            spam_ch[7],
-        ]
+        Writer::new(&mut result).u8(self.is_locked as u8)
+                                .u8(self.had_overwrite as u8)
+                                .u64(self.allow)
+                                .u64(self.deny);
+
+        result
     }
 }
 
-impl Serialize for Server {
-    const SIZE: usize = 32;
-    type Output = [u8; 32];
+impl Serialize for Lock {
+    const SIZE: usize = 18;
+    type Output = [u8; 18];
 
     #[inline]
     fn serialize(&self) -> Self::Output {
@@ -87,19 +1149,371 @@ impl Serialize for Server {
     }
 }
 
-impl Deserialize for Server {
+impl Deserialize for Lock {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug, Default)]
+///Server's override of command's rate limit.
+pub struct RateLimit {
+    //Number of uses within period, 0 means command is not limited.
+    pub uses: u16,
+    //Seconds
+    pub period: u64,
+}
+
+impl RateLimit {
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        Self {
+            uses: data.u16(),
+            period: data.u64(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u16(self.uses)
+                                .u64(self.period);
+
+        result
+    }
+}
+
+impl Serialize for RateLimit {
+    const SIZE: usize = 10;
+    type Output = [u8; 10];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for RateLimit {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Behavior of reaction roles on the same message.
+pub enum RoleMode {
+    ///Role is given on reaction and taken on its removal.
+    Normal,
+    ///Only one role of message can be picked.
+    Unique,
+    ///Role is only given, e.g. to accept rules.
+    Verify,
+}
+
+impl RoleMode {
+    pub const ALL: [RoleMode; 3] = [RoleMode::Normal, RoleMode::Unique, RoleMode::Verify];
+
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => RoleMode::Unique,
+            2 => RoleMode::Verify,
+            _ => RoleMode::Normal,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            RoleMode::Normal => 0,
+            RoleMode::Unique => 1,
+            RoleMode::Verify => 2,
+        }
+    }
+
+    #[inline]
+    pub const fn name(self) -> &'static str {
+        match self {
+            RoleMode::Normal => "normal",
+            RoleMode::Unique => "unique",
+            RoleMode::Verify => "verify",
+        }
+    }
+
+    #[inline]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for RoleMode {
+    #[inline]
+    fn default() -> Self {
+        RoleMode::Normal
+    }
+}
+
+#[derive(Debug, Default)]
+///Role, given for reaction on message.
+pub struct ReactionRole {
+    pub channel: u64,
+    //0 means there is no reaction role.
+    pub role: u64,
+    pub mode: RoleMode,
+    //Emoji as written in message, to react with it.
+    pub emoji: String,
+}
+
+impl ReactionRole {
+    //Ids, mode and length prefix take 18 bytes
+    pub const MAX_EMOJI_LEN: usize = <Self as Serialize>::SIZE - 18;
+
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        let channel = data.u64();
+        let role = data.u64();
+        let mode = RoleMode::from_u8(data.u8());
+        let len = core::cmp::min(data.u8() as usize, Self::MAX_EMOJI_LEN);
+        let emoji = data.bytes::<{ ReactionRole::MAX_EMOJI_LEN }>();
+
+        Self {
+            channel,
+            role,
+            mode,
+            emoji: String::from_utf8_lossy(&emoji[..len]).into_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let emoji = truncate(&self.emoji, Self::MAX_EMOJI_LEN);
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u64(self.channel)
+                                .u64(self.role)
+                                .u8(self.mode.to_u8())
+                                .u8(emoji.len() as u8)
+                                .bytes(emoji.as_bytes());
+
+        result
+    }
+}
+
+impl Serialize for ReactionRole {
+    const SIZE: usize = 96;
+    type Output = [u8; 96];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for ReactionRole {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Debug, Default)]
+///Roles of new members and verification gate.
+pub struct Gate {
+    //Roles, given on join, 0 means empty slot.
+    pub auto_roles: [u64; Gate::MAX_AUTO_ROLES],
+    //Channel, where members verify, 0 means gate is disabled.
+    pub channel: u64,
+    //Role, given on verification.
+    pub role: u64,
+    //Message, which members react to.
+    pub message: u64,
+    //Question, that must be answered with `verify`, empty means reaction is enough.
+    pub question: String,
+    pub answer: String,
+}
+
+impl Gate {
+    pub const MAX_AUTO_ROLES: usize = 4;
+    pub const MAX_QUESTION: usize = 128;
+    pub const MAX_ANSWER: usize = 64;
+
+    #[inline]
+    ///Returns whether new members must verify.
+    pub const fn is_enabled(&self) -> bool {
+        self.channel != 0 && self.role != 0
+    }
+
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        let mut auto_roles = [0; Self::MAX_AUTO_ROLES];
+        for role in auto_roles.iter_mut() {
+            *role = data.u64();
+        }
+        let channel = data.u64();
+        let role = data.u64();
+        let message = data.u64();
+        let question_len = core::cmp::min(data.u8() as usize, Self::MAX_QUESTION);
+        let question = data.bytes::<{ Gate::MAX_QUESTION }>();
+        let answer_len = core::cmp::min(data.u8() as usize, Self::MAX_ANSWER);
+        let answer = data.bytes::<{ Gate::MAX_ANSWER }>();
+
+        Self {
+            auto_roles,
+            channel,
+            role,
+            message,
+            question: String::from_utf8_lossy(&question[..question_len]).into_owned(),
+            answer: String::from_utf8_lossy(&answer[..answer_len]).into_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let question = truncate(&self.question, Self::MAX_QUESTION);
+        let answer = truncate(&self.answer, Self::MAX_ANSWER);
+        let mut question_bytes = [0u8; Self::MAX_QUESTION];
+        question_bytes[..question.len()].copy_from_slice(question.as_bytes());
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u64s(&self.auto_roles)
+                                .u64(self.channel)
+                                .u64(self.role)
+                                .u64(self.message)
+                                .u8(question.len() as u8)
+                                .bytes(&question_bytes)
+                                .u8(answer.len() as u8)
+                                .bytes(answer.as_bytes());
+
+        result
+    }
+}
+
+impl Serialize for Gate {
+    const SIZE: usize = 250;
+    type Output = [u8; 250];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for Gate {
+    #[inline]
+    fn deserialize(data: &Self::Output) -> Self {
+        Self::from_bytes(data)
+    }
+}
+
+#[derive(Clone, Debug)]
+///Layout of server's welcome card.
+pub struct WelcomeCard {
+    //Left edge of text box in pixels.
+    pub x: u16,
+    //Top edge of text box in pixels.
+    pub y: u16,
+    //Width of text box, in which text is centered.
+    pub width: u16,
+    pub font_size: u16,
+    //RGB, also used for avatar's border.
+    pub color: [u8; 3],
+    //Top left corner of avatar.
+    pub avatar_x: u16,
+    pub avatar_y: u16,
+    //Diameter of avatar, 0 means it is not drawn.
+    pub avatar_size: u16,
+}
+
+impl WelcomeCard {
+    #[inline]
+    pub const fn new() -> Self {
+        //Layout of default background
+        Self {
+            x: 460,
+            y: 364,
+            width: 440,
+            font_size: 35,
+            color: [238, 183, 149],
+            avatar_x: 606,
+            avatar_y: 36,
+            avatar_size: 128,
+        }
+    }
+
+    #[inline]
+    pub fn from_bytes(data: &<Self as Serialize>::Output) -> Self {
+        let mut data = Reader::new(data);
+
+        Self {
+            x: data.u16(),
+            y: data.u16(),
+            width: data.u16(),
+            font_size: data.u16(),
+            color: data.bytes(),
+            avatar_x: data.u16(),
+            avatar_y: data.u16(),
+            avatar_size: data.u16(),
+        }
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> <Self as Serialize>::Output {
+        let mut result = [0u8; <Self as Serialize>::SIZE];
+
+        Writer::new(&mut result).u16(self.x)
+                                .u16(self.y)
+                                .u16(self.width)
+                                .u16(self.font_size)
+                                .bytes(&self.color)
+                                .u16(self.avatar_x)
+                                .u16(self.avatar_y)
+                                .u16(self.avatar_size);
+
+        result
+    }
+}
+
+impl Serialize for WelcomeCard {
+    const SIZE: usize = 17;
+    type Output = [u8; 17];
+
+    #[inline]
+    fn serialize(&self) -> Self::Output {
+        self.to_bytes()
+    }
+}
+
+impl Deserialize for WelcomeCard {
     #[inline]
     fn deserialize(data: &Self::Output) -> Self {
         Self::from_bytes(data)
     }
 }
 
+impl Default for WelcomeCard {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug)]
 pub struct User {
     pub cash: u32,
     pub exp: u32,
     //since epoch
     pub last_allowance: time::Duration,
+    //minutes spent in voice channels
+    pub voice_time: u32,
 }
 
 impl User {
@@ -109,6 +1523,7 @@ impl User {
             cash: 100,
             exp: 0,
             last_allowance: time::Duration::from_secs(0),
+            voice_time: 0,
         }
     }
 
@@ -120,6 +1535,7 @@ impl User {
             last_allowance: time::Duration::from_secs(u64::from_le_bytes([
                     data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15]
             ])),
+            voice_time: u32::from_le_bytes([data[16], data[17], data[18], data[19]]),
         }
     }
 
@@ -128,6 +1544,7 @@ impl User {
         let cash = self.cash.to_le_bytes();
         let exp = self.exp.to_le_bytes();
         let last_allowance = self.last_allowance.as_secs().to_le_bytes();
+        let voice_time = self.voice_time.to_le_bytes();
 
         [
             cash[0],
@@ -146,13 +1563,17 @@ impl User {
             last_allowance[5],
             last_allowance[6],
             last_allowance[7],
+            voice_time[0],
+            voice_time[1],
+            voice_time[2],
+            voice_time[3],
         ]
     }
 }
 
 impl Serialize for User {
-    const SIZE: usize = 16;
-    type Output = [u8; 16];
+    const SIZE: usize = 20;
+    type Output = [u8; 20];
 
     #[inline]
     fn serialize(&self) -> Self::Output {
@@ -9,17 +9,33 @@ use crate::utils::OptionExt;
 pub struct DbView {
     pub user: sled::Tree,
     pub server: sled::Tree,
+    pub channel: sled::Tree,
+    pub template: sled::Tree,
+    pub permission: sled::Tree,
+    pub case: sled::Tree,
+    pub expiry: sled::Tree,
+    pub departure: sled::Tree,
+    pub automod: sled::Tree,
+    pub blocklist: sled::Tree,
+    pub raid: sled::Tree,
+    pub lock: sled::Tree,
+    pub reaction_role: sled::Tree,
+    pub gate: sled::Tree,
+    pub welcome_card: sled::Tree,
+    pub background: sled::Tree,
+    pub rate_limit: sled::Tree,
 }
 
 impl DbView {
-    pub fn delete<T: Tag>(&self, id: u64) {
+    pub fn delete<T: Tag>(&self, id: T::Key) {
         let mut retry = 5;
 
         loop {
-            if let Err(error) = T::view(self).remove(id.to_be_bytes()) {
-                match retry {
+            match T::view(self).remove(id.to_key()) {
+                Ok(_) => break,
+                Err(error) => match retry {
                     0 => {
-                        rogu::error!("Unable to delete data for id={} into storage. Error: {}", id, error);
+                        rogu::error!("Unable to delete data for id={:?} into storage. Error: {}", id, error);
                         break;
                     },
                     _ => retry -= 1,
@@ -28,15 +44,15 @@ impl DbView {
         }
     }
 
-    pub fn put<T: Tag>(&self, id: u64, data: &T) {
+    pub fn put<T: Tag>(&self, id: T::Key, data: &T) {
         let mut retry = 5;
 
         loop {
-            match T::view(self).insert(id.to_be_bytes(), data.serialize().as_ref()) {
+            match T::view(self).insert(id.to_key(), data.serialize().as_ref()) {
                 Ok(_) => break,
                 Err(error) => match retry {
                     0 => {
-                        rogu::error!("Unable to put data for id={} into storage. Error: {}", id, error);
+                        rogu::error!("Unable to put data for id={:?} into storage. Error: {}", id, error);
                         break;
                     },
                     _ => {
@@ -47,24 +63,29 @@ impl DbView {
         }
     }
 
-    pub fn get<T: Tag>(&self, id: u64) -> Result<T, sled::Error> {
+    ///Puts data only if there is no entry with the same id, returning whether it is inserted.
+    pub fn put_new<T: Tag>(&self, id: T::Key, data: &T) -> Result<bool, sled::Error> {
+        match T::view(self).compare_and_swap(id.to_key(), None::<&[u8]>, Some(data.serialize().as_ref())) {
+            Ok(result) => Ok(result.is_ok()),
+            Err(error) => {
+                rogu::error!("Unable to put data for id={:?} into storage. Error: {}", id, error);
+                Err(error)
+            }
+        }
+    }
+
+    pub fn get<T: Tag>(&self, id: T::Key) -> Result<T, sled::Error> {
         let mut retry = 5;
         loop {
-            match T::view(self).get(id.to_be_bytes()) {
-                Ok(Some(result)) => {
-                    let result: &[u8] = result.as_ref();
-                    if result.len() != <T as data::Serialize>::SIZE {
+            match T::view(self).get(id.to_key()) {
+                Ok(Some(result)) => match decode(result.as_ref()) {
+                    Some(result) => break Ok(result),
+                    None => {
                         rogu::warn!("Schema break!");
-                        let _ = T::view(self).remove(id.to_be_bytes());
+                        let _ = T::view(self).remove(id.to_key());
                         //TODO: consider using format that would work just fine with extending it (e.g.  json)
                         //but these formats are overhead
                         break Ok(T::default())
-                    } else {
-                        let result = result.as_ptr() as *const T::Output;
-                        let result = unsafe {
-                            result.as_ref().unwrap_certain()
-                        };
-                        break Ok(T::deserialize(result))
                     }
                 },
                 Ok(None) => break Ok(T::default()),
@@ -77,13 +98,228 @@ impl DbView {
             }
         }
     }
+
+    ///Iterates over all valid entries, which key starts with `prefix`.
+    pub fn scan<T: Tag, P: Key>(&self, prefix: P) -> impl Iterator<Item = (T::Key, T)> {
+        T::view(self).scan_prefix(prefix.to_key()).filter_map(|result| match result {
+            Ok((key, result)) => match (<T::Key as Key>::from_key(key.as_ref()), decode(result.as_ref())) {
+                (Some(key), Some(result)) => Some((key, result)),
+                _ => None,
+            },
+            Err(error) => {
+                rogu::error!("Unable to read storage: {}", error);
+                None
+            }
+        })
+    }
+
+    ///Returns last valid entry, which key starts with `prefix`.
+    pub fn last<T: Tag, P: Key>(&self, prefix: P) -> Option<(T::Key, T)> {
+        T::view(self).scan_prefix(prefix.to_key()).rev().filter_map(|result| match result {
+            Ok((key, result)) => match (<T::Key as Key>::from_key(key.as_ref()), decode(result.as_ref())) {
+                (Some(key), Some(result)) => Some((key, result)),
+                _ => None,
+            },
+            Err(error) => {
+                rogu::error!("Unable to read storage: {}", error);
+                None
+            }
+        }).next()
+    }
+
+    ///Iterates over all valid entries in the namespace, ordered by key.
+    pub fn entries<T: Tag>(&self) -> impl Iterator<Item = (T::Key, T)> {
+        T::view(self).iter().filter_map(|result| match result {
+            Ok((key, result)) => match (<T::Key as Key>::from_key(key.as_ref()), decode(result.as_ref())) {
+                (Some(key), Some(result)) => Some((key, result)),
+                _ => None,
+            },
+            Err(error) => {
+                rogu::error!("Unable to read storage: {}", error);
+                None
+            }
+        })
+    }
+
+    ///Iterates over all valid entries in the namespace.
+    pub fn iter<T: Tag>(&self) -> impl Iterator<Item = T> {
+        T::view(self).iter().values().filter_map(|result| match result {
+            Ok(result) => decode(result.as_ref()),
+            Err(error) => {
+                rogu::error!("Unable to read storage: {}", error);
+                None
+            }
+        })
+    }
+
+    ///Removes all entries, which key starts with `prefix`.
+    pub fn delete_prefix<T: Tag, P: Key>(&self, prefix: P) {
+        let view = T::view(self);
+        for key in view.scan_prefix(prefix.to_key()).keys() {
+            let result = key.and_then(|key| view.remove(key));
+            if let Err(error) = result {
+                rogu::error!("Unable to delete data for prefix={:?} from storage. Error: {}", prefix, error);
+            }
+        }
+    }
+
+    ///Removes everything stored for server.
+    pub fn delete_server(&self, server: u64) {
+        self.delete::<data::Server>(server);
+        self.delete::<data::AutoMod>(server);
+        self.delete::<data::Raid>(server);
+        self.delete::<data::Gate>(server);
+        self.delete::<data::WelcomeCard>(server);
+        self.delete_prefix::<data::Permission, _>(server);
+        self.delete_prefix::<data::Template, _>(server);
+        self.delete_prefix::<data::Case, _>(server);
+        self.delete_prefix::<data::BlockPattern, _>(server);
+        self.delete_prefix::<data::Lock, _>(server);
+        self.delete_prefix::<data::ReactionRole, _>(server);
+        self.delete_prefix::<data::RateLimit, _>(server);
+        self.delete_background(server);
+    }
+
+    ///Returns server's welcome background, which is stored as encoded image of variable size.
+    pub fn background(&self, server: u64) -> Option<sled::IVec> {
+        match self.background.get(server.to_key()) {
+            Ok(result) => result,
+            Err(error) => {
+                rogu::error!("Unable to read background of server {}: {}", server, error);
+                None
+            }
+        }
+    }
+
+    pub fn put_background(&self, server: u64, data: &[u8]) {
+        if let Err(error) = self.background.insert(server.to_key(), data) {
+            rogu::error!("Unable to put background of server {}: {}", server, error);
+        }
+    }
+
+    pub fn delete_background(&self, server: u64) {
+        if let Err(error) = self.background.remove(server.to_key()) {
+            rogu::error!("Unable to delete background of server {}: {}", server, error);
+        }
+    }
+}
+
+fn decode<T: Tag>(result: &[u8]) -> Option<T> {
+    if result.len() > <T as data::Serialize>::SIZE {
+        None
+    } else if result.len() < <T as data::Serialize>::SIZE {
+        //Schema is only extended by appending new fields at the end,
+        //so missing fields are treated as zeroed
+        let mut extended = vec![0u8; <T as data::Serialize>::SIZE];
+        extended[..result.len()].copy_from_slice(result);
+        let result = extended.as_ptr() as *const T::Output;
+        let result = unsafe {
+            result.as_ref().unwrap_certain()
+        };
+        Some(T::deserialize(result))
+    } else {
+        let result = result.as_ptr() as *const T::Output;
+        let result = unsafe {
+            result.as_ref().unwrap_certain()
+        };
+        Some(T::deserialize(result))
+    }
+}
+
+///Storage key.
+///
+///Encoded as big endian in order to keep entries of the same prefix (e.g. server) together.
+pub trait Key: Copy + core::fmt::Debug {
+    type Bytes: AsRef<[u8]>;
+
+    fn to_key(self) -> Self::Bytes;
+    fn from_key(key: &[u8]) -> Option<Self>;
+}
+
+#[inline]
+fn read_id(key: &[u8], idx: usize) -> Option<u64> {
+    let mut result = [0u8; 8];
+    result.copy_from_slice(key.get(idx * 8..(idx + 1) * 8)?);
+    Some(u64::from_be_bytes(result))
+}
+
+impl Key for u64 {
+    type Bytes = [u8; 8];
+
+    #[inline]
+    fn to_key(self) -> Self::Bytes {
+        self.to_be_bytes()
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Option<Self> {
+        read_id(key, 0)
+    }
+}
+
+impl Key for (u64, u64) {
+    type Bytes = [u8; 16];
+
+    #[inline]
+    fn to_key(self) -> Self::Bytes {
+        let mut result = [0u8; 16];
+        result[..8].copy_from_slice(&self.0.to_be_bytes());
+        result[8..].copy_from_slice(&self.1.to_be_bytes());
+        result
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Option<Self> {
+        Some((read_id(key, 0)?, read_id(key, 1)?))
+    }
+}
+
+impl Key for (u64, u64, u64) {
+    type Bytes = [u8; 24];
+
+    #[inline]
+    fn to_key(self) -> Self::Bytes {
+        let mut result = [0u8; 24];
+        result[..8].copy_from_slice(&self.0.to_be_bytes());
+        result[8..16].copy_from_slice(&self.1.to_be_bytes());
+        result[16..].copy_from_slice(&self.2.to_be_bytes());
+        result
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Option<Self> {
+        Some((read_id(key, 0)?, read_id(key, 1)?, read_id(key, 2)?))
+    }
+}
+
+impl Key for (u64, u64, u64, u64) {
+    type Bytes = [u8; 32];
+
+    #[inline]
+    fn to_key(self) -> Self::Bytes {
+        let mut result = [0u8; 32];
+        result[..8].copy_from_slice(&self.0.to_be_bytes());
+        result[8..16].copy_from_slice(&self.1.to_be_bytes());
+        result[16..24].copy_from_slice(&self.2.to_be_bytes());
+        result[24..].copy_from_slice(&self.3.to_be_bytes());
+        result
+    }
+
+    #[inline]
+    fn from_key(key: &[u8]) -> Option<Self> {
+        Some((read_id(key, 0)?, read_id(key, 1)?, read_id(key, 2)?, read_id(key, 3)?))
+    }
 }
 
 pub trait Tag: data::Deserialize + Default {
+    type Key: Key;
+
     fn view(view: &DbView) -> &sled::Tree;
 }
 
 impl Tag for data::User {
+    type Key = u64;
+
     #[inline]
     fn view(view: &DbView) -> &sled::Tree {
         &view.user
@@ -91,12 +327,149 @@ impl Tag for data::User {
 }
 
 impl Tag for data::Server {
+    type Key = u64;
+
     #[inline]
     fn view(view: &DbView) -> &sled::Tree {
         &view.server
     }
 }
 
+impl Tag for data::Channel {
+    type Key = u64;
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.channel
+    }
+}
+
+impl Tag for data::Permission {
+    //(server, command, target)
+    type Key = (u64, u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.permission
+    }
+}
+
+impl Tag for data::Template {
+    //(server, kind)
+    type Key = (u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.template
+    }
+}
+
+impl Tag for data::Case {
+    //(server, case number)
+    type Key = (u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.case
+    }
+}
+
+impl Tag for data::Expiry {
+    //(expiration time, server, user, action)
+    type Key = (u64, u64, u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.expiry
+    }
+}
+
+impl Tag for data::Departure {
+    //(cleanup time, server, user)
+    type Key = (u64, u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.departure
+    }
+}
+
+impl Tag for data::AutoMod {
+    type Key = u64;
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.automod
+    }
+}
+
+impl Tag for data::BlockPattern {
+    //(server, hash of pattern)
+    type Key = (u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.blocklist
+    }
+}
+
+impl Tag for data::Raid {
+    type Key = u64;
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.raid
+    }
+}
+
+impl Tag for data::Lock {
+    //(server, channel)
+    type Key = (u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.lock
+    }
+}
+
+impl Tag for data::ReactionRole {
+    //(server, message, emoji)
+    type Key = (u64, u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.reaction_role
+    }
+}
+
+impl Tag for data::Gate {
+    type Key = u64;
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.gate
+    }
+}
+
+impl Tag for data::WelcomeCard {
+    type Key = u64;
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.welcome_card
+    }
+}
+
+impl Tag for data::RateLimit {
+    //(server, command)
+    type Key = (u64, u64);
+
+    #[inline]
+    fn view(view: &DbView) -> &sled::Tree {
+        &view.rate_limit
+    }
+}
+
 pub struct Db {
     #[allow(unused)]
     db: sled::Db,
@@ -113,12 +486,42 @@ impl Db {
 
         let user = db.open_tree("user")?;
         let server = db.open_tree("server")?;
+        let channel = db.open_tree("channel")?;
+        let template = db.open_tree("template")?;
+        let permission = db.open_tree("permission")?;
+        let case = db.open_tree("case")?;
+        let expiry = db.open_tree("expiry")?;
+        let departure = db.open_tree("departure")?;
+        let automod = db.open_tree("automod")?;
+        let blocklist = db.open_tree("blocklist")?;
+        let raid = db.open_tree("raid")?;
+        let lock = db.open_tree("lock")?;
+        let reaction_role = db.open_tree("reaction_role")?;
+        let gate = db.open_tree("gate")?;
+        let welcome_card = db.open_tree("welcome_card")?;
+        let background = db.open_tree("background")?;
+        let rate_limit = db.open_tree("rate_limit")?;
 
         Ok(Self {
             db,
             view: DbView {
                 user,
                 server,
+                channel,
+                template,
+                permission,
+                case,
+                expiry,
+                departure,
+                automod,
+                blocklist,
+                raid,
+                lock,
+                reaction_role,
+                gate,
+                welcome_card,
+                background,
+                rate_limit,
             },
         })
     }
@@ -138,5 +541,65 @@ impl Drop for Db {
         if let Err(error) = self.view.server.flush() {
             rogu::error!("Failed to flush server table: {}", error);
         }
+
+        if let Err(error) = self.view.channel.flush() {
+            rogu::error!("Failed to flush channel table: {}", error);
+        }
+
+        if let Err(error) = self.view.template.flush() {
+            rogu::error!("Failed to flush template table: {}", error);
+        }
+
+        if let Err(error) = self.view.permission.flush() {
+            rogu::error!("Failed to flush permission table: {}", error);
+        }
+
+        if let Err(error) = self.view.case.flush() {
+            rogu::error!("Failed to flush case table: {}", error);
+        }
+
+        if let Err(error) = self.view.expiry.flush() {
+            rogu::error!("Failed to flush expiry table: {}", error);
+        }
+
+        if let Err(error) = self.view.departure.flush() {
+            rogu::error!("Failed to flush departure table: {}", error);
+        }
+
+        if let Err(error) = self.view.automod.flush() {
+            rogu::error!("Failed to flush automod table: {}", error);
+        }
+
+        if let Err(error) = self.view.blocklist.flush() {
+            rogu::error!("Failed to flush blocklist table: {}", error);
+        }
+
+        if let Err(error) = self.view.raid.flush() {
+            rogu::error!("Failed to flush raid table: {}", error);
+        }
+
+        if let Err(error) = self.view.lock.flush() {
+            rogu::error!("Failed to flush lock table: {}", error);
+        }
+
+        if let Err(error) = self.view.reaction_role.flush() {
+            rogu::error!("Failed to flush reaction_role table: {}", error);
+        }
+
+        if let Err(error) = self.view.gate.flush() {
+            rogu::error!("Failed to flush gate table: {}", error);
+        }
+
+        if let Err(error) = self.view.welcome_card.flush() {
+            rogu::error!("Failed to flush welcome_card table: {}", error);
+        }
+
+        if let Err(error) = self.view.background.flush() {
+            rogu::error!("Failed to flush background table: {}", error);
+        }
+
+        if let Err(error) = self.view.rate_limit.flush() {
+            rogu::error!("Failed to flush rate_limit table: {}", error);
+        }
     }
 }
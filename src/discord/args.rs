@@ -0,0 +1,238 @@
+//! Command arguments
+
+use super::HandlerContext;
+use crate::lang::Msg;
+use serenity::model::prelude::{ChannelId, RoleId, UserId};
+
+use core::{fmt, time};
+
+#[derive(Debug, PartialEq)]
+pub enum ArgError {
+    Missing(&'static str),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::Missing(name) => write!(f, "Missing {}", name),
+            ArgError::Invalid(name) => write!(f, "Invalid {}", name),
+        }
+    }
+}
+
+///Argument, that can be parsed from single word.
+pub trait Arg<'a>: Sized {
+    fn parse(text: &'a str) -> Option<Self>;
+}
+
+impl<'a> Arg<'a> for &'a str {
+    #[inline]
+    fn parse(text: &'a str) -> Option<Self> {
+        Some(text)
+    }
+}
+
+macro_rules! impl_int_arg {
+    ($($typ:ty),+) => {$(
+        impl<'a> Arg<'a> for $typ {
+            #[inline]
+            fn parse(text: &'a str) -> Option<Self> {
+                text.parse().ok()
+            }
+        }
+    )+}
+}
+
+impl_int_arg!(u8, u16, u32, u64, i64);
+
+impl<'a> Arg<'a> for time::Duration {
+    #[inline]
+    fn parse(text: &'a str) -> Option<Self> {
+        crate::utils::parse_duration(text)
+    }
+}
+
+#[inline]
+fn parse_mention(text: &str, prefix: &str) -> Option<u64> {
+    match text.strip_prefix(prefix).and_then(|text| text.strip_suffix('>')) {
+        Some(id) => id.parse().ok(),
+        None => text.parse().ok(),
+    }
+}
+
+impl<'a> Arg<'a> for UserId {
+    #[inline]
+    fn parse(text: &'a str) -> Option<Self> {
+        parse_mention(text, "<@!").or_else(|| parse_mention(text, "<@")).map(UserId)
+    }
+}
+
+impl<'a> Arg<'a> for RoleId {
+    #[inline]
+    fn parse(text: &'a str) -> Option<Self> {
+        parse_mention(text, "<@&").map(RoleId)
+    }
+}
+
+impl<'a> Arg<'a> for ChannelId {
+    #[inline]
+    fn parse(text: &'a str) -> Option<Self> {
+        parse_mention(text, "<#").map(ChannelId)
+    }
+}
+
+///Arguments parser.
+///
+///Words are separated by whitespaces, unless enclosed in double quotes.
+#[derive(Clone)]
+pub struct Args<'a> {
+    text: &'a str,
+}
+
+impl<'a> Args<'a> {
+    #[inline]
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text: text.trim(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    ///Returns next word, if any.
+    pub fn word(&mut self) -> Option<&'a str> {
+        if self.text.is_empty() {
+            return None;
+        }
+
+        let (word, rest) = match self.text.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => match self.text.find(char::is_whitespace) {
+                Some(end) => (&self.text[..end], &self.text[end..]),
+                None => (self.text, ""),
+            },
+        };
+
+        self.text = rest.trim_start();
+        Some(word)
+    }
+
+    ///Parses next word as mandatory argument.
+    pub fn next<T: Arg<'a>>(&mut self, name: &'static str) -> Result<T, ArgError> {
+        match self.word() {
+            Some(word) => T::parse(word).ok_or(ArgError::Invalid(name)),
+            None => Err(ArgError::Missing(name)),
+        }
+    }
+
+    ///Parses next word as optional argument.
+    pub fn optional<T: Arg<'a>>(&mut self, name: &'static str) -> Result<Option<T>, ArgError> {
+        match self.word() {
+            Some(word) => T::parse(word).map(Some).ok_or(ArgError::Invalid(name)),
+            None => Ok(None),
+        }
+    }
+
+    ///Parses next word, only if it is valid argument, otherwise leaves it for further parsing.
+    pub fn maybe<T: Arg<'a>>(&mut self) -> Option<T> {
+        let mut next = self.clone();
+        let result = next.word().and_then(T::parse)?;
+        *self = next;
+        Some(result)
+    }
+
+    ///Returns remaining text as it is, which can be empty.
+    #[inline]
+    pub fn rest(&mut self) -> &'a str {
+        core::mem::replace(&mut self.text, "")
+    }
+
+    ///Returns remaining text as mandatory argument.
+    pub fn text(&mut self, name: &'static str) -> Result<&'a str, ArgError> {
+        match self.rest() {
+            "" => Err(ArgError::Missing(name)),
+            text => Ok(text),
+        }
+    }
+}
+
+impl HandlerContext<'_> {
+    ///Replies with argument error and command's usage.
+    pub async fn reply_usage(&self, error: ArgError) -> serenity::Result<()> {
+        let error = match error {
+            ArgError::Missing(name) => self.tr_with(Msg::ArgMissing, &[("name", &name)]),
+            ArgError::Invalid(name) => self.tr_with(Msg::ArgInvalid, &[("name", &name)]),
+        };
+        self.reply(self.tr_with(Msg::ArgUsage, &[("error", &error), ("usage", &self.usage)])).await
+    }
+}
+
+///Unwraps argument or replies with usage error.
+macro_rules! try_arg {
+    ($ctx:expr, $arg:expr) => {
+        match $arg {
+            Ok(arg) => arg,
+            Err(error) => return $ctx.reply_usage(error).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_split_words() {
+        let mut args = Args::new("  one \"two three\"   four \"five");
+
+        assert_eq!(args.word(), Some("one"));
+        assert_eq!(args.word(), Some("two three"));
+        assert_eq!(args.word(), Some("four"));
+        assert_eq!(args.word(), Some("five"));
+        assert_eq!(args.word(), None);
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn should_parse_typed_args() {
+        let mut args = Args::new("<@!1> <@2> <@&3> <#4> 5 1h30m \"some text\" rest of  text");
+
+        assert_eq!(args.next::<UserId>("user"), Ok(UserId(1)));
+        assert_eq!(args.next::<UserId>("user"), Ok(UserId(2)));
+        assert_eq!(args.next::<RoleId>("role"), Ok(RoleId(3)));
+        assert_eq!(args.next::<ChannelId>("channel"), Ok(ChannelId(4)));
+        assert_eq!(args.next::<u32>("count"), Ok(5));
+        assert_eq!(args.next::<time::Duration>("duration"), Ok(time::Duration::from_secs(90 * 60)));
+        assert_eq!(args.next::<&str>("text"), Ok("some text"));
+        assert_eq!(args.text("rest"), Ok("rest of  text"));
+        assert_eq!(args.text("rest"), Err(ArgError::Missing("rest")));
+        assert_eq!(args.optional::<u32>("count"), Ok(None));
+    }
+
+    #[test]
+    fn should_parse_optional_args_in_middle() {
+        let mut args = Args::new("<@1> 1d being rude");
+        assert_eq!(args.next::<UserId>("user"), Ok(UserId(1)));
+        assert_eq!(args.maybe::<time::Duration>(), Some(time::Duration::from_secs(24 * 60 * 60)));
+        assert_eq!(args.maybe::<time::Duration>(), None);
+        assert_eq!(args.rest(), "being rude");
+    }
+
+    #[test]
+    fn should_fail_invalid_args() {
+        let mut args = Args::new("<@&1> <#2> -1 abc");
+
+        assert_eq!(args.next::<UserId>("user"), Err(ArgError::Invalid("user")));
+        assert_eq!(args.next::<RoleId>("role"), Err(ArgError::Invalid("role")));
+        assert_eq!(args.optional::<u32>("count"), Err(ArgError::Invalid("count")));
+        assert_eq!(args.next::<time::Duration>("duration"), Err(ArgError::Invalid("duration")));
+        assert_eq!(args.next::<u32>("count"), Err(ArgError::Missing("count")));
+    }
+}
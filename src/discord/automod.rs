@@ -0,0 +1,617 @@
+//! Automatic moderation of messages
+
+use super::*;
+use super::args::{Args, ArgError};
+use crate::data::{AutoMod, BlockPattern, Case, CaseAction, Expiry, Filter, FilterAction, PermissionTarget};
+
+use std::sync::Arc;
+use std::time;
+
+//Shorter messages cannot be considered shouting
+const MIN_CAPS_LETTERS: usize = 8;
+//Period, within which the same message is considered repeated
+const SPAM_PERIOD: time::Duration = time::Duration::from_secs(30);
+//Number of tracked users, after which stale ones are removed
+const CLEANUP_THRESHOLD: usize = 1024;
+//Limit on compiled size of patterns
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+//Hosts, which are not considered external links
+const DISCORD_HOSTS: [&str; 4] = ["discord.com", "discordapp.com", "discordapp.net", "discord.gg"];
+const INVITES: [&str; 3] = ["discord.gg/", "discord.com/invite/", "discordapp.com/invite/"];
+
+//Word is matched as whole, unless it starts or ends with non-latin character
+fn word_pattern(word: &str) -> String {
+    let start = if word.starts_with(|ch: char| ch.is_ascii_alphanumeric()) { r"\b" } else { "" };
+    let end = if word.ends_with(|ch: char| ch.is_ascii_alphanumeric()) { r"\b" } else { "" };
+    format!("{}{}{}", start, regex::escape(word), end)
+}
+
+///Checks that pattern can be used in blocklist.
+pub fn validate_regex(pattern: &str) -> Result<(), regex::Error> {
+    regex::RegexBuilder::new(pattern).size_limit(REGEX_SIZE_LIMIT).build().map(|_| ())
+}
+
+///Server's blocked words and patterns, compiled into single set.
+pub struct Blocklist {
+    set: Option<regex::RegexSet>,
+}
+
+impl Blocklist {
+    ///Compiles patterns, failing if the whole set exceeds size limit.
+    fn try_new<'a, I: IntoIterator<Item = &'a BlockPattern>>(patterns: I) -> Result<Self, regex::Error> {
+        let patterns: Vec<_> = patterns.into_iter().map(|pattern| match pattern.is_regex {
+            true => pattern.text.clone(),
+            false => word_pattern(&pattern.text),
+        }).collect();
+
+        if patterns.is_empty() {
+            return Ok(Self {
+                set: None
+            });
+        }
+
+        let set = regex::RegexSetBuilder::new(&patterns).case_insensitive(true).size_limit(REGEX_SIZE_LIMIT).build()?;
+        Ok(Self {
+            set: Some(set),
+        })
+    }
+
+    fn new<'a, I: IntoIterator<Item = &'a BlockPattern>>(patterns: I) -> Self {
+        match Self::try_new(patterns) {
+            Ok(blocklist) => blocklist,
+            Err(error) => {
+                rogu::warn!("Unable to compile blocklist: {}", error);
+                Self {
+                    set: None
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn is_match(&self, text: &str) -> bool {
+        self.set.as_ref().map(|set| set.is_match(text)).unwrap_or(false)
+    }
+}
+
+//Returns percentage of uppercase letters, if text is long enough.
+fn caps_percent(text: &str) -> Option<u16> {
+    let mut letters = 0;
+    let mut upper = 0;
+    for ch in text.chars() {
+        if ch.is_uppercase() {
+            upper += 1;
+            letters += 1;
+        } else if ch.is_lowercase() {
+            letters += 1;
+        }
+    }
+
+    match letters >= MIN_CAPS_LETTERS {
+        true => Some((upper * 100 / letters) as u16),
+        false => None,
+    }
+}
+
+//Counts mentions of users and roles, including everyone and here.
+fn count_mentions(text: &str) -> usize {
+    text.matches("<@").count() + text.matches("@everyone").count() + text.matches("@here").count()
+}
+
+//Counts unicode and custom emoji.
+fn count_emoji(text: &str) -> usize {
+    let custom = text.split('<').skip(1).filter(|part| {
+        let emoji = match part.strip_prefix(':').or_else(|| part.strip_prefix("a:")) {
+            Some(emoji) => emoji,
+            None => return false,
+        };
+
+        match emoji.split('>').next().and_then(|emoji| emoji.rsplit_once(':')) {
+            Some((name, id)) => !name.is_empty() && !id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit()),
+            None => false,
+        }
+    }).count();
+
+    let unicode = text.chars().filter(|ch| match *ch as u32 {
+        //Skin tone modifiers are part of previous emoji
+        0x1F3FB..=0x1F3FF => false,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF => true,
+        _ => false,
+    }).count();
+
+    custom + unicode
+}
+
+fn has_invite(text: &str) -> bool {
+    let text = text.to_lowercase();
+    INVITES.iter().any(|invite| text.contains(invite))
+}
+
+fn has_external_link(text: &str) -> bool {
+    let text = text.to_lowercase();
+    text.match_indices("http").filter_map(|(idx, _)| {
+        let link = &text[idx..];
+        link.strip_prefix("https://").or_else(|| link.strip_prefix("http://"))
+    }).any(|link| {
+        let host = link.split(|ch: char| ch == '/' || ch == '>' || ch == ':' || ch.is_whitespace()).next().unwrap_or("");
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        !host.is_empty() && !DISCORD_HOSTS.iter().any(|discord| host == *discord || host.ends_with(&format!(".{}", discord)))
+    })
+}
+
+//Returns first enabled filter, that catches message.
+fn check(settings: &AutoMod, blocklist: &Blocklist, text: &str, repeats: u16) -> Option<Filter> {
+    Filter::ALL.iter().copied().filter(|filter| settings.rule(*filter).action != FilterAction::Off).find(|filter| {
+        let limit = settings.limit(*filter) as usize;
+        match filter {
+            Filter::Words => blocklist.is_match(text),
+            Filter::Invites => has_invite(text),
+            Filter::Links => has_external_link(text),
+            Filter::Caps => caps_percent(text).map(|percent| percent as usize >= limit).unwrap_or(false),
+            Filter::Mentions => count_mentions(text) >= limit,
+            Filter::Emoji => count_emoji(text) >= limit,
+            Filter::Spam => repeats as usize >= limit,
+        }
+    })
+}
+
+struct Recent {
+    hash: u64,
+    count: u16,
+    last: time::Instant,
+}
+
+#[derive(Default)]
+///Tracks repeated messages per (server, user)
+pub(super) struct SpamTracker {
+    recent: std::sync::Mutex<HashMap<(u64, u64), Recent>>,
+}
+
+impl SpamTracker {
+    ///Records message, returning number of times it has been repeated in a row.
+    fn record(&self, key: (u64, u64), text: &str, now: time::Instant) -> u16 {
+        let hash = xxhash_rust::xxh3::xxh3_64(text.trim().to_lowercase().as_bytes());
+        let mut recent = match self.recent.lock() {
+            Ok(recent) => recent,
+            Err(error) => error.into_inner(),
+        };
+
+        if recent.len() >= CLEANUP_THRESHOLD {
+            recent.retain(|_, entry| now.saturating_duration_since(entry.last) < SPAM_PERIOD);
+        }
+
+        let entry = recent.entry(key).or_insert(Recent {
+            hash,
+            count: 0,
+            last: now,
+        });
+
+        if entry.hash != hash || now.saturating_duration_since(entry.last) >= SPAM_PERIOD {
+            entry.hash = hash;
+            entry.count = 0;
+        }
+        entry.count = entry.count.saturating_add(1);
+        entry.last = now;
+
+        entry.count
+    }
+}
+
+struct Settings<'a>(&'a AutoMod);
+
+impl fmt::Display for Settings<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for filter in Filter::ALL.iter().copied() {
+            write!(f, "`{}`: {}", filter.name(), self.0.rule(filter).action.name())?;
+            match self.0.limit(filter) {
+                0 => f.write_str("\n")?,
+                limit => writeln!(f, " ({})", limit)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Exemptions<'a>(&'a AutoMod);
+
+impl fmt::Display for Exemptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = self.0.exempt_channels.iter().filter(|id| **id != 0).map(|id| ChannelId(*id).mention().to_string());
+        let roles = self.0.exempt_roles.iter().filter(|id| **id != 0).map(|id| RoleId(*id).mention().to_string());
+
+        let mut exemptions = channels.chain(roles);
+        match exemptions.next() {
+            Some(exemption) => f.write_str(&exemption)?,
+            None => return f.write_str("None"),
+        }
+
+        for exemption in exemptions {
+            write!(f, ", {}", exemption)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Handler {
+    async fn blocklist(&self, server: u64) -> Arc<Blocklist> {
+        if let Some(blocklist) = self.blocklists.read().await.get(&server) {
+            return blocklist.clone();
+        }
+
+        let patterns: Vec<_> = self.state.db.scan::<BlockPattern, _>(server).map(|(_, pattern)| pattern).collect();
+        let blocklist = Arc::new(Blocklist::new(&patterns));
+        self.blocklists.write().await.insert(server, blocklist.clone());
+        blocklist
+    }
+
+    ///Applies auto-moderation filters to message, returning whether it has been removed.
+    pub(super) async fn auto_moderate(&self, ctx: &Context, msg: &Message, server_info: &data::Server) -> bool {
+        let server = match msg.guild_id {
+            Some(server) => server,
+            None => return false,
+        };
+
+        let settings = match self.state.db.get::<AutoMod>(server.0) {
+            Ok(settings) if settings.is_enabled() => settings,
+            Ok(_) => return false,
+            Err(error) => {
+                rogu::error!("Unable to get auto-mod settings: {}", error);
+                return false;
+            }
+        };
+
+        let roles = msg.member.as_ref().map(|member| member.roles.as_slice()).unwrap_or(&[]);
+        let role_ids: Vec<_> = roles.iter().map(|role| role.0).collect();
+        if settings.is_exempt(msg.channel_id.0, &role_ids) || self.is_moderator_by_roles(server.0, msg.author.id, roles).await {
+            return false;
+        }
+
+        let repeats = match settings.rule(Filter::Spam).action {
+            FilterAction::Off => 0,
+            _ => self.spam.record((server.0, msg.author.id.0), &msg.content, time::Instant::now()),
+        };
+        let blocklist = match settings.rule(Filter::Words).action {
+            FilterAction::Off => Arc::new(Blocklist::new(&[])),
+            _ => self.blocklist(server.0).await,
+        };
+
+        let filter = match check(&settings, &blocklist, &msg.content, repeats) {
+            Some(filter) => filter,
+            None => return false,
+        };
+        let action = settings.rule(filter).action;
+
+        self.own_deletions.mark(&[msg.id]);
+        if let Err(error) = msg.delete(ctx).await {
+            self.own_deletions.unmark(&[msg.id]);
+            rogu::warn!("Unable to delete message caught by {} filter: {}", filter.name(), error);
+        }
+        self.log_auto_mod(ctx, server, msg, filter, action).await;
+
+        let duration = time::Duration::from_secs(settings.mute_duration);
+        let case_action = match action {
+            FilterAction::Off | FilterAction::Delete => return true,
+            FilterAction::Warn => CaseAction::Warn,
+            FilterAction::Mute if server_info.mute_role == 0 => {
+                rogu::warn!("Server {} has no mute role, cannot mute by auto-mod", server.0);
+                return true;
+            },
+            FilterAction::Mute => match ctx.http.add_member_role(server.0, msg.author.id.0, server_info.mute_role).await {
+                Ok(()) => CaseAction::Mute,
+                Err(error) => {
+                    rogu::warn!("Unable to mute user {}: {}", msg.author.id.0, error);
+                    return true;
+                }
+            },
+        };
+
+        let case = Case {
+            action: case_action,
+            target: msg.author.id.0,
+            moderator: self.state.info.id,
+            time: time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0),
+            duration: match case_action {
+                CaseAction::Mute => duration.as_secs(),
+                _ => 0,
+            },
+            reason: format!("Auto-mod: {}", filter.name()),
+        };
+
+        let db = self.state.db.clone();
+        let (server_id, target, role) = (server.0, msg.author.id.0, server_info.mute_role);
+        let _ = tokio::task::spawn_blocking(move || {
+            if case.action == CaseAction::Mute {
+                scheduler::schedule(&db, server_id, target, Expiry { action: CaseAction::Mute, role }, duration);
+            }
+            moderation::open_case(&db, server_id, &case)
+        }).await;
+
+        if case_action == CaseAction::Mute {
+            let data = ctx.data.read().await;
+            if let Some(sender) = data.get::<SchedulerSendTag>() {
+                let _ = sender.send(scheduler::SchedulerCommand::Refresh).await;
+            }
+        }
+
+        let text = crate::utils::render_template(Msg::AutoModNotice.get(server_info.lang), &[("user", &msg.author.mention()), ("filter", &filter.name())]);
+        if let Err(error) = msg.channel_id.say(ctx, text).await {
+            rogu::warn!("Unable to notify about auto-mod: {}", error);
+        }
+
+        true
+    }
+
+    #[inline]
+    pub async fn handle_automod(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let id = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut settings = match self.state.db.get::<AutoMod>(id) {
+            Ok(settings) => settings,
+            Err(error) => {
+                rogu::error!("Unable to get auto-mod settings: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        match args.word() {
+            None => {
+                ctx.reply_embed_private(|m| {
+                    m.title("Auto-mod")
+                     .field("Filters", Settings(&settings), false)
+                     .field("Mute duration", crate::utils::format_duration(time::Duration::from_secs(settings.mute_duration)), false)
+                     .field("Exemptions", Exemptions(&settings), false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("duration") => match try_arg!(ctx, args.next::<time::Duration>("duration")).as_secs() {
+                0 => return ctx.reply_usage(ArgError::Invalid("duration")).await,
+                duration => settings.mute_duration = duration,
+            },
+            Some(filter) => {
+                let filter = match Filter::from_name(filter) {
+                    Some(filter) => filter,
+                    None => return ctx.reply_usage(ArgError::Invalid("filter")).await,
+                };
+                let action = match FilterAction::from_name(try_arg!(ctx, args.next::<&str>("action"))) {
+                    Some(action) => action,
+                    None => return ctx.reply_usage(ArgError::Invalid("action")).await,
+                };
+                let limit = try_arg!(ctx, args.optional::<u16>("limit"));
+
+                let rule = settings.rule_mut(filter);
+                rule.action = action;
+                if let Some(limit) = limit {
+                    rule.limit = limit;
+                }
+            },
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &settings)).await;
+
+        ctx.react(emoji::OK).await
+    }
+
+    #[inline]
+    pub async fn handle_blocklist(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let action = try_arg!(ctx, args.next::<&str>("action"));
+
+        let id = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let is_regex = match action {
+            "list" => {
+                let mut text = String::new();
+                for (_, pattern) in self.state.db.scan::<BlockPattern, _>(id) {
+                    match pattern.is_regex {
+                        true => text.push_str(&format!("- regex `{}`\n", pattern.text)),
+                        false => text.push_str(&format!("- `{}`\n", pattern.text)),
+                    }
+                }
+
+                if text.is_empty() {
+                    return ctx.reply(ctx.tr(Msg::BlocklistEmpty)).await;
+                }
+
+                ctx.reply_embed_private(|m| m.title("Blocklist").description(text)).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            "add" => false,
+            "regex" => true,
+            "remove" => {
+                let pattern = try_arg!(ctx, args.text("pattern"));
+                let key = (id, xxhash_rust::xxh3::xxh3_64(pattern.as_bytes()));
+                match self.state.db.get::<BlockPattern>(key) {
+                    //Missing pattern is default one without text
+                    Ok(pattern) if !pattern.text.is_empty() => {
+                        let db = self.state.db.clone();
+                        let _ = tokio::task::spawn_blocking(move || db.delete::<BlockPattern>(key)).await;
+                    },
+                    _ => return ctx.reply(ctx.tr(Msg::PatternNotFound)).await,
+                }
+
+                self.blocklists.write().await.remove(&id);
+                return ctx.react(emoji::OK).await;
+            },
+            _ => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        };
+
+        let pattern = try_arg!(ctx, args.text("pattern"));
+        if pattern.len() > BlockPattern::MAX_LEN {
+            return ctx.reply(ctx.tr_with(Msg::PatternTooLong, &[("max", &BlockPattern::MAX_LEN)])).await;
+        }
+        if is_regex {
+            if let Err(error) = validate_regex(pattern) {
+                return ctx.reply(ctx.tr_with(Msg::BadRegex, &[("error", &error)])).await;
+            }
+        }
+
+        let key = (id, xxhash_rust::xxh3::xxh3_64(pattern.as_bytes()));
+        let pattern = BlockPattern {
+            is_regex,
+            text: pattern.to_owned(),
+        };
+
+        //Patterns, that are valid alone, may exceed size limit together, which would turn filter off
+        let patterns: Vec<_> = self.state.db.scan::<BlockPattern, _>(id).filter(|(other, _)| *other != key).map(|(_, pattern)| pattern).collect();
+        if let Err(error) = Blocklist::try_new(patterns.iter().chain(core::iter::once(&pattern))) {
+            return ctx.reply(ctx.tr_with(Msg::BadRegex, &[("error", &error)])).await;
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(key, &pattern)).await;
+        self.blocklists.write().await.remove(&id);
+
+        ctx.react(emoji::OK).await
+    }
+
+    #[inline]
+    pub async fn handle_exempt(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let target = try_arg!(ctx, Args::new(ctx.args).text("target"));
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        let id = guild.id.0;
+
+        let mut settings = match self.state.db.get::<AutoMod>(id) {
+            Ok(settings) => settings,
+            Err(error) => {
+                rogu::error!("Unable to get auto-mod settings: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let (slots, target) = match super::permission::find_target(target, &guild) {
+            Some((PermissionTarget::Channel, target)) => (&mut settings.exempt_channels, target),
+            Some((PermissionTarget::Role, target)) => (&mut settings.exempt_roles, target),
+            Some((PermissionTarget::User, _)) | None => return ctx.reply(ctx.tr(Msg::TargetNotFound)).await,
+        };
+
+        //Toggle exemption
+        if let Some(slot) = slots.iter_mut().find(|slot| **slot == target) {
+            *slot = 0;
+        } else if let Some(slot) = slots.iter_mut().find(|slot| **slot == 0) {
+            *slot = target;
+        } else {
+            return ctx.reply(ctx.tr_with(Msg::TooManyExemptions, &[("max", &AutoMod::MAX_EXEMPT)])).await;
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &settings)).await;
+
+        ctx.react(emoji::OK).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_caps() {
+        assert_eq!(caps_percent("HELLO EVERYONE"), Some(100));
+        assert_eq!(caps_percent("Hello Everyone"), Some(15));
+        //Too short
+        assert_eq!(caps_percent("HELLO!"), None);
+    }
+
+    #[test]
+    fn should_count_mentions_and_emoji() {
+        assert_eq!(count_mentions("<@1> <@!2> <@&3> @everyone"), 4);
+        assert_eq!(count_mentions("mail@example.com"), 0);
+
+        assert_eq!(count_emoji("😀😀 <:pog:123> <a:dance:456>"), 4);
+        assert_eq!(count_emoji("👍🏻"), 1);
+        assert_eq!(count_emoji("<not:emoji> <:bad:> text"), 0);
+    }
+
+    #[test]
+    fn should_detect_links() {
+        assert!(has_invite("join discord.gg/abc"));
+        assert!(has_invite("https://Discord.com/invite/abc"));
+        assert!(!has_invite("https://discord.com/channels/1/2/3"));
+
+        assert!(has_external_link("see https://example.com/page"));
+        assert!(has_external_link("<http://www.evil.net>"));
+        assert!(!has_external_link("https://discord.com/channels/1/2/3"));
+        assert!(!has_external_link("https://cdn.discordapp.com/attachments/1.png"));
+        assert!(!has_external_link("no links here, just http talk"));
+    }
+
+    #[test]
+    fn should_match_blocklist() {
+        let patterns = [
+            BlockPattern {
+                is_regex: false,
+                text: "bad".to_owned(),
+            },
+            BlockPattern {
+                is_regex: false,
+                text: "ばか".to_owned(),
+            },
+            BlockPattern {
+                is_regex: true,
+                text: r"fr+ee\s+nitro".to_owned(),
+            },
+        ];
+        let blocklist = Blocklist::new(&patterns);
+
+        assert!(blocklist.is_match("that is BAD"));
+        assert!(!blocklist.is_match("badge"));
+        assert!(blocklist.is_match("おまえはばかだ"));
+        assert!(blocklist.is_match("Frrree  nitro here"));
+        assert!(!blocklist.is_match("nitro"));
+        assert!(!Blocklist::new(&[]).is_match("bad"));
+    }
+
+    #[test]
+    fn should_count_repeated_messages() {
+        let tracker = SpamTracker::default();
+        let now = time::Instant::now();
+
+        assert_eq!(tracker.record((1, 2), "hello", now), 1);
+        assert_eq!(tracker.record((1, 2), "Hello ", now + time::Duration::from_secs(1)), 2);
+        assert_eq!(tracker.record((1, 3), "hello", now + time::Duration::from_secs(1)), 1);
+        assert_eq!(tracker.record((1, 2), "bye", now + time::Duration::from_secs(2)), 1);
+        assert_eq!(tracker.record((1, 2), "bye", now + SPAM_PERIOD + time::Duration::from_secs(3)), 1);
+    }
+
+    #[test]
+    fn should_check_enabled_filters_only() {
+        let mut settings = AutoMod::default();
+        let blocklist = Blocklist::new(&[]);
+
+        assert_eq!(check(&settings, &blocklist, "HELLO EVERYONE", 5), None);
+
+        settings.rule_mut(Filter::Caps).action = FilterAction::Delete;
+        assert_eq!(check(&settings, &blocklist, "HELLO EVERYONE", 1), Some(Filter::Caps));
+        assert_eq!(check(&settings, &blocklist, "Hello everyone", 5), None);
+
+        settings.rule_mut(Filter::Spam).action = FilterAction::Warn;
+        assert_eq!(check(&settings, &blocklist, "Hello everyone", 2), None);
+        assert_eq!(check(&settings, &blocklist, "Hello everyone", 3), Some(Filter::Spam));
+    }
+}
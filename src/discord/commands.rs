@@ -1,6 +1,8 @@
 use super::*;
+use super::args::{Args, ArgError};
 use crate::game::Level;
 use crate::utils;
+use crate::utils::OptionExt;
 
 use std::time;
 use core::fmt;
@@ -9,55 +11,31 @@ use core::fmt;
 pub const ALLOWANCE_COOL_DOWN: u64 = 60 * 60;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-mod err {
-    pub mod discord {
-        pub const NO_USER_INFO: &str = "Cannot find your dossier :(";
-        pub const BROKEN_TIME: &str = "My watch is broken, cannot do it now";
-    }
-}
-
-pub const PING: u64 = xxhash_rust::const_xxh3::xxh3_64(b"ping");
-pub const HELP: u64 = xxhash_rust::const_xxh3::xxh3_64(b"help");
-pub const ROLL: u64 = xxhash_rust::const_xxh3::xxh3_64(b"roll");
-pub const JUDGE: u64 = xxhash_rust::const_xxh3::xxh3_64(b"judge");
-pub const WHOAMI: u64 = xxhash_rust::const_xxh3::xxh3_64(b"whoami");
-pub const ALLOWANCE: u64 = xxhash_rust::const_xxh3::xxh3_64(b"allowance");
-pub const PLAYER: u64 = xxhash_rust::const_xxh3::xxh3_64(b"player");
-pub const SUGGEST: u64 = xxhash_rust::const_xxh3::xxh3_64(b"suggest");
-pub const SHUTDOWN: u64 = xxhash_rust::const_xxh3::xxh3_64(b"shutdown");
-pub const RESTART: u64 = xxhash_rust::const_xxh3::xxh3_64(b"restart");
-pub const CONFIG: u64 = xxhash_rust::const_xxh3::xxh3_64(b"config");
-pub const SET_WELCOME: u64 = xxhash_rust::const_xxh3::xxh3_64(b"set_welcome");
-pub const SET_VOICE: u64 = xxhash_rust::const_xxh3::xxh3_64(b"set_voice");
-pub const SET_DEV: u64 = xxhash_rust::const_xxh3::xxh3_64(b"set_dev");
-pub const SET_SPAM: u64 = xxhash_rust::const_xxh3::xxh3_64(b"set_spam");
-
 //Normally you should prefer to return future, but most of commands are too complicated to avoid
 //type erasure, hence hope compiler is able to inline async
 impl super::Handler {
     #[inline]
-    pub async fn handle_roll(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        //format_args is not thread safe for await
-        struct Text(Result<cute_dnd_dice::Roll, cute_dnd_dice::ParseError>);
-
-        impl fmt::Display for Text {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                match self.0.as_ref() {
-                    Ok(roll) => write!(f, "Roll {}: {}", roll, roll.roll()),
-                    Err(error) => write!(f, "Cannot recognize dice: {}", error)
-                }
-            }
-        }
+    pub async fn handle_ping(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        ctx.reply(ctx.tr(Msg::Pong)).await
+    }
 
-        let roll = cute_dnd_dice::Roll::from_str(&ctx.text[4..]);
-        ctx.msg.reply(ctx, Text(roll)).await?;
+    #[inline]
+    pub async fn handle_roll(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let dice = try_arg!(ctx, Args::new(ctx.args).text("dice"));
+        let text = match cute_dnd_dice::Roll::from_str(dice) {
+            Ok(roll) => ctx.tr_with(Msg::Roll, &[("dice", &roll), ("result", &roll.roll())]),
+            Err(error) => ctx.tr_with(Msg::BadDice, &[("error", &error)]),
+        };
+        ctx.reply(text).await?;
         Ok(())
     }
 
     #[inline]
-    pub async fn handle_judge(&self, ctx: HandlerContext<'_>, args: Vec<&'_ str>) -> serenity::Result<()> {
+    pub async fn handle_judge(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let args: Vec<&str> = core::iter::from_fn(|| args.word()).collect();
         if args.len() < 2 {
-            ctx.msg.reply(&ctx, "You need to give me at least two choices.").await?;
+            ctx.reply(ctx.tr(Msg::NeedChoices)).await?;
             return Ok(())
         }
 
@@ -66,51 +44,46 @@ impl super::Handler {
                                             cute_dnd_dice::Modifier::Plus(0));
         let choice = args[roll.roll() as usize - 1];
 
-        ctx.msg.reply(&ctx, format!("The criminal is {}", choice)).await?;
+        ctx.reply(ctx.tr_with(Msg::Judge, &[("choice", &choice)])).await?;
         Ok(())
     }
 
     #[inline]
-    pub async fn handle_player<'a, T: Iterator<Item=&'a str>>(&self, ctx: HandlerContext<'a>, mut args: T) -> serenity::Result<()> {
+    pub async fn handle_player(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
         const COST: u32 = 5;
         const START: u64 = xxhash_rust::const_xxh3::xxh3_64(b"start");
         const STOP: u64 = xxhash_rust::const_xxh3::xxh3_64(b"stop");
 
-        let cmd = match args.next() {
-            Some(cmd) => cmd,
-            None => {
-                ctx.msg.reply(&ctx, "Player has following commands: start, stop").await?;
-                return Ok(())
-            },
-        };
+        let mut args = Args::new(ctx.args);
+        let cmd = try_arg!(ctx, args.next::<&str>("command"));
 
-        let id = if let Some(id) = ctx.msg.guild_id.as_ref().map(|id| id.0) {
+        let id = if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
             id
         } else {
-            let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+            let _ = ctx.react(emoji::KINSHI).await;
             return Ok(());
         };
 
         if !self.state.db.get::<data::Server>(id).map(|server| server.music_ch != 0).unwrap_or(false) {
-            ctx.msg.reply(&ctx, "Voice channel is not set yet, please do so.").await?;
+            ctx.reply(ctx.tr(Msg::MusicNoChannel)).await?;
             return Ok(())
         }
 
         match xxhash_rust::xxh3::xxh3_64(cmd.as_bytes()) {
-            START => match args.next() {
-                Some(music) => {
-                    let user_id = ctx.msg.author.id.0;
+            START => match args.next::<&str>("link") {
+                Ok(music) => {
+                    let user_id = ctx.author.id.0;
                     let mut user = match self.state.db.get::<data::User>(user_id) {
                         Ok(user) => user,
                         Err(error) => {
                             rogu::error!("Cannot retrieve user info: {}", error);
-                            ctx.msg.reply(&ctx, "Cannot access your wallet :(").await?;
+                            ctx.reply(ctx.tr(Msg::NoWallet)).await?;
                             return Ok(())
                         }
                     };
 
                     if user.cash < COST {
-                        ctx.msg.reply(&ctx, "You do not have enough cash(5¥) for music :P").await?;
+                        ctx.reply(ctx.tr_with(Msg::MusicNoCash, &[("cost", &COST)])).await?;
                         return Ok(())
                     }
 
@@ -126,21 +99,21 @@ impl super::Handler {
                                 if let Err(error) = sender.send(player::PlayerCommand::Play(id, music)).await {
                                     rogu::error!("Player unexpectedly stopped: {}", error);
                                 } else {
-                                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                                    let _ = ctx.react(emoji::OK).await;
                                     return Ok(());
                                 }
                             }
-                            let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                            let _ = ctx.react(emoji::KINSHI).await;
                         },
                         Err(_) => {
-                            ctx.msg.reply(&ctx, "Cannot download it, is this a youtube link?").await?;
+                            ctx.reply(ctx.tr(Msg::MusicBadLink)).await?;
                         },
                     }
 
                     guard.forget();
                 },
-                None => {
-                    ctx.msg.reply(&ctx, "Play requires something to play, provide link to music").await?;
+                Err(error) => {
+                    ctx.reply_usage(error).await?;
                 }
             },
             STOP => match ctx.is_mod {
@@ -150,18 +123,18 @@ impl super::Handler {
                         if let Err(error) = sender.send(player::PlayerCommand::Stop(id)).await {
                             rogu::error!("Player unexpectedly stopped: {}", error);
                         } else {
-                            let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                            let _ = ctx.react(emoji::OK).await;
                             return Ok(());
                         }
                     }
-                    let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                    let _ = ctx.react(emoji::KINSHI).await;
                 },
                 false => {
-                    let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                    let _ = ctx.react(emoji::KINSHI).await;
                 }
             },
             _ => {
-                ctx.msg.reply(&ctx, "Unknown command, allowed: start, stop").await?;
+                ctx.reply(ctx.tr(Msg::PlayerUnknown)).await?;
             },
         }
 
@@ -172,10 +145,12 @@ impl super::Handler {
     pub async fn handle_suggest(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
         const COST: u32 = 10;
 
-        let id = match ctx.msg.guild_id.as_ref().map(|id| id.0) {
+        let suggestion = try_arg!(ctx, Args::new(ctx.args).text("suggestion"));
+
+        let id = match ctx.guild_id.as_ref().map(|id| id.0) {
             Some(id) => id,
             None => {
-                let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                let _ = ctx.react(emoji::KINSHI).await;
                 return Ok(());
             },
         };
@@ -183,23 +158,23 @@ impl super::Handler {
         let channel = match self.state.db.get::<data::Server>(id) {
             Ok(server) if server.dev_ch != 0 => ChannelId(server.dev_ch),
             _ => {
-                ctx.msg.reply(&ctx, "Dev channel is not set yet, please ask mods.").await?;
+                ctx.reply(ctx.tr(Msg::NoDevChannel)).await?;
                 return Ok(())
             },
         };
 
-        let user_id = ctx.msg.author.id.0;
+        let user_id = ctx.author.id.0;
         let mut user = match self.state.db.get::<data::User>(user_id) {
             Ok(user) => user,
             Err(error) => {
                 rogu::error!("Cannot retrieve user info: {}", error);
-                ctx.msg.reply(&ctx, "Cannot access your wallet :(").await?;
+                ctx.reply(ctx.tr(Msg::NoWallet)).await?;
                 return Ok(())
             }
         };
 
         if user.cash < COST {
-            ctx.msg.reply(&ctx, "You do not have enough cash(10¥) to post suggestion").await?;
+            ctx.reply(ctx.tr_with(Msg::SuggestNoCash, &[("cost", &COST)])).await?;
             return Ok(())
         }
 
@@ -208,17 +183,16 @@ impl super::Handler {
         let db = self.state.db.clone();
         let guard = utils::DropGuard::new(move || db.put(user_id, &user), utils::DropAsync);
 
-        let suggestion = &ctx.msg.content[8..];
         let mut author: serenity::builder::CreateEmbedAuthor = Default::default();
-        author.name(ctx.msg.author.name.as_str());
-        let user_image = if let Some(icon) = ctx.msg.author.avatar.as_ref() {
-            let user_image = format!("https://cdn.discordapp.com/avatars/{}/{}.png", ctx.msg.author.id.0, icon);
+        author.name(ctx.author.name.as_str());
+        let user_image = if let Some(icon) = ctx.author.avatar.as_ref() {
+            let user_image = format!("https://cdn.discordapp.com/avatars/{}/{}.png", ctx.author.id.0, icon);
             author.icon_url(&user_image);
             Some(user_image)
         } else {
             None
         };
-        let user_name = ctx.msg.author.name.as_str();
+        let user_name = ctx.author.name.as_str();
 
         let result = channel.send_message(&ctx.serenity, move |msg| msg.embed(|m| {
             if let Some(user_image) = user_image {
@@ -233,10 +207,10 @@ impl super::Handler {
 
         if let Err(error) = result {
             rogu::error!("Failed to post suggestion: {}", error);
-            ctx.msg.reply(&ctx, "I'm sorry I cannot post your suggestion :(").await?;
+            ctx.reply(ctx.tr(Msg::SuggestFailed)).await?;
             guard.forget();
         } else {
-            let _ = ctx.msg.react(&ctx, emoji::OK).await;
+            let _ = ctx.react(emoji::OK).await;
         }
 
         Ok(())
@@ -244,29 +218,35 @@ impl super::Handler {
 
     #[inline]
     pub async fn handle_whoami(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        match self.state.db.get::<data::User>(ctx.msg.author.id.0) {
+        struct VoiceTime(u32);
+        impl fmt::Display for VoiceTime {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}h {}m", self.0 / 60, self.0 % 60)
+            }
+        }
+
+        match self.state.db.get::<data::User>(ctx.author.id.0) {
             Ok(user) => {
-                let result = ctx.msg.author.direct_message(&ctx, |m| {
-                    m.embed(|m| {
-                        let level = Level::new(user.exp);
-                        m.title("Profile")
-                         .field("Level", level.level, true)
-                         .field("Exp", level, true)
-                         .field("Cash", user.cash, false)
-                         .field("Moderator:", ctx.is_mod, false)
-                    })
+                let result = ctx.reply_embed_private(|m| {
+                    let level = Level::new(user.exp);
+                    m.title("Profile")
+                     .field("Level", level.level, true)
+                     .field("Exp", level, true)
+                     .field("Cash", user.cash, false)
+                     .field("Voice time", VoiceTime(user.voice_time), false)
+                     .field("Moderator:", ctx.is_mod, false)
                 }).await;
 
                 if result.is_ok() {
-                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                    let _ = ctx.react(emoji::OK).await;
                 } else {
-                    let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                    let _ = ctx.react(emoji::KINSHI).await;
                 }
                 result
             },
             Err(error) => {
                 rogu::error!("Unable to get user's info: {}", error);
-                ctx.msg.reply(ctx, err::discord::NO_USER_INFO).await
+                ctx.reply(ctx.tr(Msg::NoUserInfo)).await
             },
         }?;
 
@@ -275,14 +255,7 @@ impl super::Handler {
 
     #[inline]
     pub async fn handle_allowance(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        struct Allowance(u32);
-        impl fmt::Display for Allowance {
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(f, "Your allowance is {}¥", self.0)
-            }
-        }
-
-        let id = ctx.msg.author.id.0;
+        let id = ctx.author.id.0;
         match self.state.db.get::<data::User>(id) {
             Ok(mut user) => match time::SystemTime::UNIX_EPOCH.checked_add(user.last_allowance) {
                 Some(before) => {
@@ -297,23 +270,23 @@ impl super::Handler {
                             let db = self.state.db.clone();
                             let _ = tokio::task::spawn_blocking(move || db.put(id, &user)).await;
 
-                            ctx.msg.reply(&ctx, Allowance(allowance)).await?;
+                            ctx.reply(ctx.tr_with(Msg::Allowance, &[("amount", &allowance)])).await?;
                             return Ok(())
                         }
                     }
                     //error case can only happen if for some reason now is in past
 
-                    let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+                    let _ = ctx.react(emoji::KINSHI).await;
                 },
                 None => {
                     //well I suppose we're too far in future so fix god damn system time
                     rogu::error!("Time is broken");
-                    ctx.msg.reply(ctx, err::discord::BROKEN_TIME).await?;
+                    ctx.reply(ctx.tr(Msg::BrokenTime)).await?;
                 }
             },
             Err(error) => {
                 rogu::error!("Unable to get user's info: {}", error);
-                ctx.msg.reply(ctx, err::discord::NO_USER_INFO).await?;
+                ctx.reply(ctx.tr(Msg::NoUserInfo)).await?;
             },
         }
 
@@ -322,17 +295,12 @@ impl super::Handler {
 
     #[inline]
     pub async fn handle_help(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        let result = ctx.msg.author.direct_message(&ctx, |msg| {
-            if ctx.is_mod {
-                msg.content(format_args!("{}\n{}", CMD_HELP_TXT, MOD_CMD_HELP_TXT))
-            } else {
-                msg.content(CMD_HELP_TXT)
-            }
-        }).await;
+        let help = self.help_text(&ctx);
+        let result = ctx.reply_private(help).await;
 
         match result {
             Ok(_) => {
-                let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                let _ = ctx.react(emoji::OK).await;
                 Ok(())
             },
             Err(err) => Err(err)
@@ -341,170 +309,660 @@ impl super::Handler {
 
     #[inline]
     pub async fn handle_config(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            if let Some(id) = ctx.msg.guild_id.as_ref().map(|id| id.0) {
-                if let Ok(server) = self.state.db.get::<data::Server>(id) {
-                    ctx.msg.author.direct_message(&ctx, |m| {
-                        m.embed(|m| {
-                            m.title("Config")
-                             .field("Version", VERSION, false)
-                             .field("Welcome channel", server.welcome_ch, false)
-                             .field("Music channel", server.music_ch, false)
-                             .field("Dev channel", server.dev_ch, false)
-                             .field("Spam channel", server.spam_ch, false)
-                        })
-                    }).await?;
-
-                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
-                    return Ok(())
+        struct ExpEvent<'a>(&'a data::Server);
+        impl fmt::Display for ExpEvent<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match self.0.exp_event_rate {
+                    0 => f.write_str("None"),
+                    rate => write!(f, "{}% from <t:{}> to <t:{}>", rate, self.0.exp_event_start, self.0.exp_event_end),
                 }
             }
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        struct ModRoles<'a>(&'a data::Server);
+        impl fmt::Display for ModRoles<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut roles = self.0.mod_roles.iter().filter(|role| **role != 0);
+                match roles.next() {
+                    Some(role) => write!(f, "{}", RoleId(*role).mention())?,
+                    None => return f.write_str("By name or permissions"),
+                }
+
+                for role in roles {
+                    write!(f, ", {}", RoleId(*role).mention())?;
+                }
+
+                Ok(())
+            }
+        }
+
+        if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
+            if let Ok(server) = self.state.db.get::<data::Server>(id) {
+                ctx.reply_embed_private(|m| {
+                    m.title("Config")
+                     .field("Version", VERSION, false)
+                     .field("Prefix", server.prefix().unwrap_or(self.config.prefix.as_str()), false)
+                     .field("Language", server.lang.code(), false)
+                     .field("Welcome channel", server.welcome_ch, false)
+                     .field("Goodbye channel", server.goodbye_ch, false)
+                     .field("Music channel", server.music_ch, false)
+                     .field("Dev channel", server.dev_ch, false)
+                     .field("Spam channel", server.spam_ch, false)
+                     .field("Mod-log channel", server.modlog_ch, false)
+                     .field("Exp event", ExpEvent(&server), false)
+                     .field("Moderator roles", ModRoles(&server), false)
+                     .field("Mute role", RoleId(server.mute_role).mention(), false)
+                }).await?;
+
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(())
+            }
+        }
+
+        let _ = ctx.react(emoji::KINSHI).await;
         Ok(())
     }
 
     #[inline]
     pub async fn handle_restart(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            let data = ctx.serenity.data.read().await;
-            if let Some(manager) = data.get::<ShardManagerTag>() {
-                let _ = ctx.msg.react(&ctx, emoji::OK).await;
-                manager.lock().await.shutdown_all().await;
-                return Ok(());
-            }
+        let data = ctx.serenity.data.read().await;
+        if let Some(manager) = data.get::<ShardManagerTag>() {
+            let _ = ctx.react(emoji::OK).await;
+            manager.lock().await.shutdown_all().await;
+            return Ok(());
+        }
 
-            let mut data = ctx.serenity.data.write().await;
-            if let Some(sender) = data.get_mut::<PlayerSendTag>() {
-                let _ = sender.send(player::PlayerCommand::Shutdown);
-            }
+        let mut data = ctx.serenity.data.write().await;
+        if let Some(sender) = data.get_mut::<PlayerSendTag>() {
+            let _ = sender.send(player::PlayerCommand::Shutdown);
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        let _ = ctx.react(emoji::KINSHI).await;
         Ok(())
     }
 
     #[inline]
     pub async fn handle_shutdown(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
+        {
             let data = ctx.serenity.data.read().await;
             if let Some(manager) = data.get::<ShardManagerTag>() {
-                let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                let _ = ctx.react(emoji::OK).await;
                 manager.lock().await.shutdown_all().await;
                 crate::IS_SHUTDOWN.store(true, core::sync::atomic::Ordering::Release);
             }
+        }
 
-            let mut data = ctx.serenity.data.write().await;
-            if let Some(sender) = data.get_mut::<PlayerSendTag>() {
-                let _ = sender.send(player::PlayerCommand::Shutdown);
-            }
-
-            return Ok(());
+        let mut data = ctx.serenity.data.write().await;
+        if let Some(sender) = data.get_mut::<PlayerSendTag>() {
+            let _ = sender.send(player::PlayerCommand::Shutdown);
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
         Ok(())
     }
 
     #[inline]
     pub async fn handle_set_welcome(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            if let Some(id) = ctx.msg.guild_id.as_ref().map(|id| id.0) {
-                if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
-                    server.welcome_ch = if server.welcome_ch == ctx.msg.channel_id.0 {
+        if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
+            if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
+                server.welcome_ch = if server.welcome_ch == ctx.channel_id.0 {
+                    0
+                } else {
+                    ctx.channel_id.0
+                };
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(())
+            }
+        }
+
+        let _ = ctx.react(emoji::KINSHI).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_set_voice(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        if let Some(guild) = ctx.guild().await {
+            let server_id = guild.id.0;
+            if let Some(voice_ch) = guild.voice_states.get(&ctx.author.id).and_then(|state| state.channel_id) {
+                if let Ok(mut server) = self.state.db.get::<data::Server>(server_id) {
+                    server.music_ch = if server.music_ch == voice_ch.0 {
                         0
                     } else {
-                        ctx.msg.channel_id.0
+                        voice_ch.0
                     };
 
                     let db = self.state.db.clone();
-                    let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+                    let _ = tokio::task::spawn_blocking(move || db.put(server_id, &server)).await;
 
-                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
+                    let _ = ctx.react(emoji::OK).await;
                     return Ok(())
                 }
+            } else {
+                let _ = ctx.reply(ctx.tr(Msg::NotInVoice)).await;
             }
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        let _ = ctx.react(emoji::KINSHI).await;
         Ok(())
     }
 
     #[inline]
-    pub async fn handle_set_voice(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            if let Some(guild) = ctx.msg.guild(&ctx.serenity).await {
-                let server_id = guild.id.0;
-                if let Some(voice_ch) = guild.voice_states.get(&ctx.msg.author.id).and_then(|state| state.channel_id) {
-                    if let Ok(mut server) = self.state.db.get::<data::Server>(server_id) {
-                        server.music_ch = if server.music_ch == voice_ch.0 {
-                            0
-                        } else {
-                            voice_ch.0
-                        };
+    pub async fn handle_set_dev(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
+            if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
+                server.dev_ch = if server.dev_ch == ctx.channel_id.0 {
+                    0
+                } else {
+                    ctx.channel_id.0
+                };
 
-                        let db = self.state.db.clone();
-                        let _ = tokio::task::spawn_blocking(move || db.put(server_id, &server)).await;
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
 
-                        let _ = ctx.msg.react(&ctx, emoji::OK).await;
-                        return Ok(())
-                    }
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(())
+            }
+        }
+
+        let _ = ctx.react(emoji::KINSHI).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_set_spam(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
+            if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
+                server.spam_ch = if server.spam_ch == ctx.channel_id.0 {
+                    0
                 } else {
-                    let _ = ctx.msg.reply(&ctx, "You're not in any channel, please join one before using the command").await;
-                }
+                    ctx.channel_id.0
+                };
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(())
             }
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        let _ = ctx.react(emoji::KINSHI).await;
         Ok(())
     }
 
     #[inline]
-    pub async fn handle_set_dev(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            if let Some(id) = ctx.msg.guild_id.as_ref().map(|id| id.0) {
-                if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
-                    server.dev_ch = if server.dev_ch == ctx.msg.channel_id.0 {
-                        0
-                    } else {
-                        ctx.msg.channel_id.0
-                    };
+    pub async fn handle_set_modlog(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        if let Some(id) = ctx.guild_id.as_ref().map(|id| id.0) {
+            if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
+                server.modlog_ch = if server.modlog_ch == ctx.channel_id.0 {
+                    0
+                } else {
+                    ctx.channel_id.0
+                };
 
-                    let db = self.state.db.clone();
-                    let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
 
-                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
-                    return Ok(())
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(())
+            }
+        }
+
+        let _ = ctx.react(emoji::KINSHI).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_exp_event(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        //Sanity limit on how much it is possible to boost exp
+        const MAX_RATE: u16 = 1000;
+
+        let mut args = Args::new(ctx.args);
+
+        let id = match ctx.guild_id.as_ref().map(|id| id.0) {
+            Some(id) => id,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let (rate, start, end) = match try_arg!(ctx, args.next::<&str>("rate")) {
+            "off" => (0, 0, 0),
+            rate => {
+                let rate = match utils::parse_rate(rate) {
+                    Some(rate) if rate > 0 && rate <= MAX_RATE => rate,
+                    _ => {
+                        ctx.reply(ctx.tr(Msg::BadRate)).await?;
+                        return Ok(());
+                    }
+                };
+                let duration = try_arg!(ctx, args.next::<time::Duration>("duration"));
+                let delay = try_arg!(ctx, args.optional::<time::Duration>("delay")).unwrap_or_default();
+
+                let now = match time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH) {
+                    Ok(now) => now,
+                    Err(_) => {
+                        ctx.reply(ctx.tr(Msg::BrokenTime)).await?;
+                        return Ok(());
+                    }
+                };
+
+                let (start, end) = match now.checked_add(delay).and_then(|start| start.checked_add(duration).map(|end| (start, end))) {
+                    Some(times) => times,
+                    None => return ctx.reply_usage(ArgError::Invalid("duration")).await,
+                };
+                (rate, start.as_secs(), end.as_secs())
+            },
+        };
+
+        match self.state.db.get::<data::Server>(id) {
+            Ok(mut server) => {
+                server.exp_event_rate = rate;
+                server.exp_event_start = start;
+                server.exp_event_end = end;
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+                let _ = ctx.react(emoji::OK).await;
+            },
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_exp_weight(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        //Sanity limit on how much it is possible to boost exp
+        const MAX_WEIGHT: u16 = 1000;
+
+        let weight = Args::new(ctx.args).word();
+        if ctx.guild_id.is_none() {
+            let _ = ctx.react(emoji::KINSHI).await;
+            return Ok(());
+        }
+
+        let weight = match weight.map(utils::parse_rate) {
+            Some(Some(weight)) if weight <= MAX_WEIGHT => weight,
+            Some(_) => {
+                ctx.reply(ctx.tr(Msg::BadWeight)).await?;
+                return Ok(());
+            },
+            None => {
+                let weight = self.state.db.get::<data::Channel>(ctx.channel_id.0).map(|channel| channel.exp_weight).unwrap_or(100);
+                ctx.reply(ctx.tr_with(Msg::ExpWeight, &[("weight", &weight)])).await?;
+                return Ok(());
+            },
+        };
+
+        let id = ctx.channel_id.0;
+        let mut channel = match self.state.db.get::<data::Channel>(id) {
+            Ok(channel) => channel,
+            Err(error) => {
+                rogu::error!("Unable to get channel info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        channel.exp_weight = weight;
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &channel)).await;
+
+        let _ = ctx.react(emoji::OK).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_level_up(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let id = match ctx.guild_id.as_ref().map(|id| id.0) {
+            Some(id) => id,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut server = match self.state.db.get::<data::Server>(id) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        let template_id = (id, data::TemplateKind::LevelUp as u64);
+
+        match args.word() {
+            Some("dest") => {
+                server.level_up_dest = match args.word() {
+                    Some("spam") => data::LevelUpDest::Spam,
+                    Some("channel") => data::LevelUpDest::Channel,
+                    Some("dm") => data::LevelUpDest::Dm,
+                    Some("off") => data::LevelUpDest::Off,
+                    Some(_) => return ctx.reply_usage(ArgError::Invalid("destination")).await,
+                    None => return ctx.reply_usage(ArgError::Missing("destination")).await,
+                };
+            },
+            Some("image") => {
+                server.level_up_img = match args.word() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    Some(_) => return ctx.reply_usage(ArgError::Invalid("image")).await,
+                    None => return ctx.reply_usage(ArgError::Missing("image")).await,
+                };
+            },
+            Some("message") => {
+                let template = args.rest();
+                let db = self.state.db.clone();
+                match template {
+                    "" => {
+                        ctx.reply(ctx.tr(Msg::LevelUpPlaceholders)).await?;
+                        return Ok(());
+                    },
+                    "reset" => {
+                        let _ = tokio::task::spawn_blocking(move || db.delete::<data::Template>(template_id)).await;
+                    },
+                    template => {
+                        let template = data::Template::new(template);
+                        let _ = tokio::task::spawn_blocking(move || db.put(template_id, &template)).await;
+                    },
                 }
+
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(());
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("option")).await,
+            None => {
+                let template = self.state.db.get::<data::Template>(template_id).unwrap_or_default();
+                ctx.reply_embed_private(|m| {
+                    m.title("Level up")
+                     .field("Destination", format_args!("{:?}", server.level_up_dest), false)
+                     .field("Image", server.level_up_img, false)
+                     .field("Message", template.get().unwrap_or("Default"), false)
+                }).await?;
+
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(());
+            },
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+        let _ = ctx.react(emoji::OK).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_prefix(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let prefix = Args::new(ctx.args).word();
+
+        let id = match ctx.guild_id.as_ref().map(|id| id.0) {
+            Some(id) => id,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut server = match self.state.db.get::<data::Server>(id) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
             }
+        };
+
+        match prefix {
+            Some("reset") => {
+                server.set_prefix("");
+            },
+            Some(prefix) => if !server.set_prefix(prefix) {
+                ctx.reply(ctx.tr_with(Msg::PrefixTooLong, &[("max", &data::Server::MAX_PREFIX)])).await?;
+                return Ok(());
+            },
+            None => {
+                let prefix = server.prefix().unwrap_or(self.config.prefix.as_str());
+                ctx.reply(ctx.tr_with(Msg::CurrentPrefix, &[("prefix", &prefix)])).await?;
+                return Ok(());
+            },
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+        let _ = ctx.react(emoji::OK).await;
         Ok(())
     }
 
     #[inline]
-    pub async fn handle_set_spam(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        if ctx.is_mod {
-            if let Some(id) = ctx.msg.guild_id.as_ref().map(|id| id.0) {
-                if let Ok(mut server) = self.state.db.get::<data::Server>(id) {
-                    server.spam_ch = if server.spam_ch == ctx.msg.channel_id.0 {
-                        0
-                    } else {
-                        ctx.msg.channel_id.0
-                    };
+    pub async fn handle_lang(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let lang = Args::new(ctx.args).word();
 
-                    let db = self.state.db.clone();
-                    let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+        let id = match ctx.guild_id.as_ref().map(|id| id.0) {
+            Some(id) => id,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
 
-                    let _ = ctx.msg.react(&ctx, emoji::OK).await;
-                    return Ok(())
+        let mut server = match self.state.db.get::<data::Server>(id) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        server.lang = match lang.map(Lang::from_code) {
+            Some(Some(lang)) => lang,
+            Some(None) => return ctx.reply_usage(ArgError::Invalid("language")).await,
+            None => {
+                let available = Lang::ALL.iter().map(|lang| lang.code()).collect::<Vec<_>>().join(", ");
+                ctx.reply(ctx.tr_with(Msg::CurrentLang, &[("lang", &server.lang.code()), ("available", &available)])).await?;
+                return Ok(());
+            },
+        };
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+        let _ = ctx.react(emoji::OK).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_mod_role(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let is_add = match try_arg!(ctx, args.next::<&str>("action")) {
+            "add" => true,
+            "remove" => false,
+            _ => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        };
+
+        let role = match super::utils::find_role(try_arg!(ctx, args.text("role")), &guild.roles) {
+            Some(role) => role.0,
+            None => {
+                ctx.reply(ctx.tr(Msg::RoleNotFound)).await?;
+                return Ok(());
+            }
+        };
+
+        let id = guild.id.0;
+        let mut server = match self.state.db.get::<data::Server>(id) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        if is_add {
+            if server.mod_roles.contains(&role) {
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(());
+            }
+
+            match server.mod_roles.iter_mut().find(|slot| **slot == 0) {
+                Some(slot) => *slot = role,
+                None => {
+                    ctx.reply(ctx.tr_with(Msg::TooManyModRoles, &[("max", &data::Server::MAX_MOD_ROLES)])).await?;
+                    return Ok(());
+                }
+            }
+        } else {
+            match server.mod_roles.iter_mut().find(|slot| **slot == role) {
+                Some(slot) => *slot = 0,
+                None => {
+                    ctx.reply(ctx.tr(Msg::NotModRole)).await?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+        self.update_mod_roles(id, guild.owner_id, &guild.roles).await;
+
+        let _ = ctx.react(emoji::OK).await;
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_perm(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        struct Rule<'a>(&'a str, data::PermissionTarget, u64, bool);
+        impl fmt::Display for Rule<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let action = match self.3 {
+                    true => "allow",
+                    false => "deny",
+                };
+
+                match self.1 {
+                    data::PermissionTarget::Role => write!(f, "`{}` {} {}", self.0, action, RoleId(self.2).mention()),
+                    data::PermissionTarget::User => write!(f, "`{}` {} {}", self.0, action, UserId(self.2).mention()),
+                    data::PermissionTarget::Channel => write!(f, "`{}` {} {}", self.0, action, ChannelId(self.2).mention()),
                 }
             }
         }
 
-        let _ = ctx.msg.react(&ctx, emoji::KINSHI).await;
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        let id = guild.id.0;
+
+        let action = match try_arg!(ctx, args.next::<&str>("action")) {
+            "list" => {
+                let mut rules = String::new();
+                for command in registry::COMMANDS {
+                    for ((_, _, target), rule) in self.state.db.scan::<data::Permission, _>((id, command.id())) {
+                        rules.push_str(&Rule(command.name, rule.target, target, rule.allow).to_string());
+                        rules.push('\n');
+                    }
+                }
+
+                if rules.is_empty() {
+                    rules.push_str(ctx.tr(Msg::NoPermRules));
+                }
+
+                ctx.reply_embed_private(|m| m.title("Permissions").description(rules)).await?;
+                let _ = ctx.react(emoji::OK).await;
+                return Ok(());
+            },
+            "allow" => Some(true),
+            "deny" => Some(false),
+            "reset" => None,
+            _ => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        };
+
+        let cmd = match registry::find(try_arg!(ctx, args.next::<&str>("command"))) {
+            Some(cmd) if cmd.access != registry::Access::Locked => cmd,
+            Some(_) => {
+                ctx.reply(ctx.tr(Msg::CommandLocked)).await?;
+                return Ok(());
+            },
+            None => {
+                ctx.reply(ctx.tr(Msg::NoSuchCommand)).await?;
+                return Ok(());
+            },
+        };
+        let cmd_hash = cmd.id();
+
+        let target = match args.rest() {
+            "" => None,
+            target => match super::permission::find_target(target, &guild) {
+                Some(target) => Some(target),
+                None => {
+                    ctx.reply(ctx.tr(Msg::TargetNotFound)).await?;
+                    return Ok(());
+                }
+            },
+        };
+
+        //Moderation commands can be allowed only to chosen roles or users, @everyone role has the same id as server
+        let is_broad = match target {
+            Some((data::PermissionTarget::Channel, _)) => true,
+            Some((data::PermissionTarget::Role, target_id)) => target_id == id,
+            _ => false,
+        };
+        if action == Some(true) && is_broad && cmd.access == registry::Access::Mod {
+            return ctx.reply(ctx.tr(Msg::ModAllowTooBroad)).await;
+        }
+
+        let db = self.state.db.clone();
+        match (action, target) {
+            (Some(allow), Some((target, target_id))) => {
+                let rule = data::Permission {
+                    target,
+                    allow,
+                };
+                let _ = tokio::task::spawn_blocking(move || db.put((id, cmd_hash, target_id), &rule)).await;
+            },
+            (None, Some((_, target_id))) => {
+                let _ = tokio::task::spawn_blocking(move || db.delete::<data::Permission>((id, cmd_hash, target_id))).await;
+            },
+            (None, None) => {
+                let _ = tokio::task::spawn_blocking(move || {
+                    let rules: Vec<_> = db.scan::<data::Permission, _>((id, cmd_hash)).map(|(key, _)| key).collect();
+                    for rule in rules {
+                        db.delete::<data::Permission>(rule);
+                    }
+                }).await;
+            },
+            (Some(_), None) => return ctx.reply_usage(ArgError::Missing("target")).await,
+        }
+
+        let _ = ctx.react(emoji::OK).await;
         Ok(())
     }
 }
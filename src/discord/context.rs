@@ -0,0 +1,117 @@
+//! Command's origin and ways to respond to it
+
+use super::*;
+
+use serenity::builder::CreateEmbed;
+use serenity::model::interactions::InteractionApplicationCommandCallbackDataFlags;
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Clone, Copy)]
+///Origin of command.
+pub enum Source<'a> {
+    ///Prefixed message.
+    Message(&'a Message),
+    ///Slash command, with flag whether its deferred response has been replaced by reply already.
+    ///
+    ///Deferred response can be replaced only once, every subsequent reply has to be follow up.
+    Interaction(&'a ApplicationCommandInteraction, &'a AtomicBool),
+}
+
+impl HandlerContext<'_> {
+    #[inline]
+    ///Returns message in language of context.
+    pub fn tr(&self, msg: Msg) -> &'static str {
+        msg.get(self.lang)
+    }
+
+    #[inline]
+    ///Returns message in language of context, with placeholders replaced by `vars`.
+    pub fn tr_with(&self, msg: Msg, vars: &[(&str, &dyn fmt::Display)]) -> String {
+        crate::utils::render_template(self.tr(msg), vars)
+    }
+
+    ///Retrieves server of command from cache.
+    pub async fn guild(&self) -> Option<Guild> {
+        match self.guild_id {
+            Some(id) => self.serenity.cache.guild(id).await,
+            None => None,
+        }
+    }
+
+    ///Replies to command within the same channel.
+    pub async fn reply<T: fmt::Display>(&self, content: T) -> serenity::Result<()> {
+        match self.source {
+            Source::Message(msg) => msg.reply(self, content).await.map(|_| ()),
+            Source::Interaction(interaction, responded) => respond(self.serenity, interaction, responded, Some(content.to_string()), None, false).await,
+        }
+    }
+
+    ///Replies to command privately: in DM for messages and ephemeral for slash commands.
+    pub async fn reply_private<T: fmt::Display>(&self, content: T) -> serenity::Result<()> {
+        match self.source {
+            Source::Message(_) => self.author.direct_message(self, |msg| msg.content(content)).await.map(|_| ()),
+            Source::Interaction(interaction, responded) => respond(self.serenity, interaction, responded, Some(content.to_string()), None, true).await,
+        }
+    }
+
+    ///Replies to command with embed privately: in DM for messages and ephemeral for slash commands.
+    pub async fn reply_embed_private<F: FnOnce(&mut CreateEmbed) -> &mut CreateEmbed>(&self, embed: F) -> serenity::Result<()> {
+        let mut result = CreateEmbed::default();
+        embed(&mut result);
+
+        match self.source {
+            Source::Message(_) => self.author.direct_message(self, |msg| msg.set_embed(result)).await.map(|_| ()),
+            Source::Interaction(interaction, responded) => respond(self.serenity, interaction, responded, None, Some(result), true).await,
+        }
+    }
+
+    ///Acknowledges command with emoji.
+    ///
+    ///Slash commands cannot have reactions, so emoji is sent as ephemeral reply, unless command has been replied already.
+    pub async fn react(&self, emoji: char) -> serenity::Result<()> {
+        match self.source {
+            Source::Message(msg) => msg.react(self, emoji).await.map(|_| ()),
+            Source::Interaction(_, responded) if responded.load(Ordering::Acquire) => Ok(()),
+            Source::Interaction(interaction, responded) => respond(self.serenity, interaction, responded, Some(emoji.to_string()), None, true).await,
+        }
+    }
+}
+
+///Replies to slash command, which response is always deferred.
+pub(super) async fn respond(ctx: &Context, interaction: &ApplicationCommandInteraction, replied: &AtomicBool, content: Option<String>, embed: Option<CreateEmbed>, is_private: bool) -> serenity::Result<()> {
+    let flags = match is_private {
+        true => InteractionApplicationCommandCallbackDataFlags::EPHEMERAL,
+        false => InteractionApplicationCommandCallbackDataFlags::empty(),
+    };
+
+    if !replied.swap(true, Ordering::AcqRel) {
+        match is_private {
+            //Deferred response is public, so it is replaced by the first public reply
+            false => return interaction.edit_original_interaction_response(&ctx.http, |msg| {
+                if let Some(content) = content {
+                    msg.content(content);
+                }
+                if let Some(embed) = embed {
+                    msg.add_embed(embed);
+                }
+                msg
+            }).await.map(|_| ()),
+            //Otherwise it is removed in favour of ephemeral follow up
+            true => if let Err(error) = interaction.delete_original_interaction_response(&ctx.http).await {
+                rogu::warn!("Unable to remove deferred response: {}", error);
+            },
+        }
+    }
+
+    interaction.create_followup_message(&ctx.http, |msg| {
+        if let Some(content) = content {
+            msg.content(content);
+        }
+        if let Some(embed) = embed {
+            msg.add_embed(embed);
+        }
+        msg.flags(flags)
+    }).await.map(|_| ())
+}
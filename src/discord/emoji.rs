@@ -1,2 +1,3 @@
 pub const OK: char = '👌';
 pub const KINSHI: char = '🚫';
+pub const VERIFY: char = '✅';
@@ -0,0 +1,316 @@
+//! Roles of new members and verification gate
+
+use super::*;
+use super::args::{Arg, Args, ArgError};
+use crate::data::Gate;
+
+use serenity::model::channel::{Reaction, ReactionType};
+
+///Compares answers case-insensitively, ignoring extra whitespace.
+fn check_answer(expected: &str, given: &str) -> bool {
+    let mut expected = expected.split_whitespace();
+    let mut given = given.split_whitespace();
+
+    loop {
+        match (expected.next(), given.next()) {
+            (None, None) => break true,
+            (Some(expected), Some(given)) if expected.to_lowercase() == given.to_lowercase() => continue,
+            _ => break false,
+        }
+    }
+}
+
+impl Handler {
+    fn gate(&self, server: u64) -> Option<Gate> {
+        match self.state.db.get::<Gate>(server) {
+            Ok(gate) => Some(gate),
+            Err(error) => {
+                rogu::error!("Unable to get gate settings: {}", error);
+                None
+            }
+        }
+    }
+
+    ///Gives auto-roles to new member, returning whether member must verify before welcome.
+    pub(super) async fn handle_gate_join(&self, ctx: &Context, member: &Member, is_raid: bool) -> bool {
+        let gate = match self.gate(member.guild_id.0) {
+            Some(gate) => gate,
+            None => return false,
+        };
+
+        //Raid members are left to moderators
+        if !is_raid {
+            for role in gate.auto_roles.iter().filter(|role| **role != 0) {
+                if let Err(error) = ctx.http.add_member_role(member.guild_id.0, member.user.id.0, *role).await {
+                    rogu::warn!("Unable to give auto-role {} to user {}: {}", role, member.user.id.0, error);
+                }
+            }
+        }
+
+        gate.is_enabled()
+    }
+
+    ///Gives member role and posts postponed welcome.
+    async fn verify_member(&self, ctx: &Context, server: GuildId, user: &User, gate: &Gate) -> serenity::Result<()> {
+        ctx.http.add_member_role(server.0, user.id.0, gate.role).await?;
+        self.welcome_member(ctx, server, user, false).await;
+        Ok(())
+    }
+
+    ///Verifies member, who reacted to gate's message.
+    pub(super) async fn handle_gate_reaction(&self, ctx: &Context, reaction: &Reaction) {
+        let (server, user) = match (reaction.guild_id, reaction.user_id) {
+            (Some(server), Some(user)) if user.0 != self.state.info.id => (server, user),
+            _ => return,
+        };
+
+        let gate = match self.gate(server.0) {
+            Some(gate) if gate.is_enabled() && gate.message == reaction.message_id.0 => gate,
+            _ => return,
+        };
+        //Question must be answered with command
+        if !gate.question.is_empty() || reaction.emoji != ReactionType::Unicode(emoji::VERIFY.to_string()) {
+            return;
+        }
+
+        //Gate's message keeps only bot's reaction
+        let _ = reaction.delete(ctx).await;
+
+        if reaction.member.as_ref().map(|member| member.roles.contains(&RoleId(gate.role))).unwrap_or(false) {
+            return;
+        }
+        let result = match reaction.user(ctx).await {
+            Ok(user) => self.verify_member(ctx, server, &user, &gate).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            rogu::warn!("Unable to verify user {}: {}", user.0, error);
+        }
+    }
+
+    ///Posts gate's message in its channel.
+    async fn post_gate(&self, ctx: &HandlerContext<'_>, gate: &mut Gate) -> serenity::Result<()> {
+        let text = match gate.question.as_str() {
+            "" => ctx.tr_with(Msg::GateReact, &[("emoji", &emoji::VERIFY)]),
+            question => ctx.tr_with(Msg::GateQuestion, &[("question", &question)]),
+        };
+
+        let message = ChannelId(gate.channel).say(&ctx.serenity.http, text).await?;
+        gate.message = message.id.0;
+        if gate.question.is_empty() {
+            message.react(&ctx.serenity.http, emoji::VERIFY).await?;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub async fn handle_verify(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let gate = match self.gate(server.0) {
+            Some(gate) if gate.is_enabled() && gate.channel == ctx.channel_id.0 => gate,
+            _ => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        if ctx.roles.contains(&RoleId(gate.role)) {
+            return ctx.react(emoji::OK).await;
+        }
+        if !gate.question.is_empty() && !check_answer(&gate.answer, Args::new(ctx.args).rest()) {
+            return ctx.reply(ctx.tr(Msg::WrongAnswer)).await;
+        }
+
+        match self.verify_member(ctx.serenity, server, ctx.author, &gate).await {
+            Ok(()) => ctx.react(emoji::OK).await,
+            Err(error) => {
+                rogu::warn!("Unable to verify user {}: {}", ctx.author.id.0, error);
+                ctx.reply(ctx.tr_with(Msg::ActionFailed, &[("action", &"verify")])).await
+            }
+        }
+    }
+
+    #[inline]
+    pub async fn handle_autorole(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let mut gate = match self.gate(guild.id.0) {
+            Some(gate) => gate,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let is_add = match args.word() {
+            None => {
+                let mut text = String::new();
+                for role in gate.auto_roles.iter().filter(|role| **role != 0) {
+                    text.push_str(&format!("- {}\n", RoleId(*role).mention()));
+                }
+                if text.is_empty() {
+                    text.push_str("None");
+                }
+
+                ctx.reply_embed_private(|m| m.title("Auto-roles").description(text)).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("add") => true,
+            Some("remove") => false,
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        };
+
+        let role = match super::utils::find_role(try_arg!(ctx, args.text("role")), &guild.roles) {
+            Some(role) => role,
+            None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+        };
+        //Removal is allowed regardless, so that out of reach role can be cleaned up
+        if is_add && !super::utils::can_give_role(&guild, role, ctx.author.id, ctx.roles, UserId(self.state.info.id)) {
+            return ctx.reply(ctx.tr(Msg::RoleTooHigh)).await;
+        }
+        let role = role.0;
+
+        match is_add {
+            true if gate.auto_roles.contains(&role) => (),
+            true => match gate.auto_roles.iter_mut().find(|slot| **slot == 0) {
+                Some(slot) => *slot = role,
+                None => return ctx.reply(ctx.tr_with(Msg::TooManyAutoRoles, &[("max", &Gate::MAX_AUTO_ROLES)])).await,
+            },
+            false => match gate.auto_roles.iter_mut().find(|slot| **slot == role) {
+                Some(slot) => *slot = 0,
+                None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+            },
+        }
+
+        let db = self.state.db.clone();
+        let id = guild.id.0;
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &gate)).await;
+
+        ctx.react(emoji::OK).await
+    }
+
+    #[inline]
+    pub async fn handle_gate(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let mut gate = match self.gate(guild.id.0) {
+            Some(gate) => gate,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let (previous_channel, previous_message) = (gate.channel, gate.message);
+        match args.word() {
+            None => {
+                let question = match gate.question.as_str() {
+                    "" => "None".to_owned(),
+                    question => format!("{} ({})", question, gate.answer),
+                };
+
+                ctx.reply_embed_private(|m| {
+                    m.title("Verification gate")
+                     .field("Channel", ChannelId(gate.channel).mention(), false)
+                     .field("Member role", RoleId(gate.role).mention(), false)
+                     .field("Question", question, false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("channel") => {
+                gate.channel = match try_arg!(ctx, args.next::<&str>("channel")) {
+                    "off" => 0,
+                    channel => match <ChannelId as Arg>::parse(channel) {
+                        Some(channel) if guild.channels.contains_key(&channel) => channel.0,
+                        Some(_) => return ctx.reply(ctx.tr(Msg::TargetNotFound)).await,
+                        None => return ctx.reply_usage(ArgError::Invalid("channel")).await,
+                    },
+                };
+            },
+            Some("role") => {
+                gate.role = match try_arg!(ctx, args.text("role")) {
+                    "reset" => 0,
+                    role => match super::utils::find_role(role, &guild.roles) {
+                        Some(role) if super::utils::can_give_role(&guild, role, ctx.author.id, ctx.roles, UserId(self.state.info.id)) => role.0,
+                        Some(_) => return ctx.reply(ctx.tr(Msg::RoleTooHigh)).await,
+                        None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+                    },
+                };
+            },
+            Some("question") => {
+                let (question, answer) = match try_arg!(ctx, args.text("question")) {
+                    "reset" => ("", ""),
+                    text => match text.split_once('|') {
+                        Some((question, answer)) if !question.trim().is_empty() && !answer.trim().is_empty() => (question.trim(), answer.trim()),
+                        _ => return ctx.reply_usage(ArgError::Invalid("question")).await,
+                    },
+                };
+
+                if question.len() > Gate::MAX_QUESTION || answer.len() > Gate::MAX_ANSWER {
+                    return ctx.reply(ctx.tr_with(Msg::QuestionTooLong, &[("question", &Gate::MAX_QUESTION), ("answer", &Gate::MAX_ANSWER)])).await;
+                }
+
+                gate.question = question.to_owned();
+                gate.answer = answer.to_owned();
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("setting")).await,
+        }
+
+        //Message reflects current question and is only present while gate is enabled
+        if previous_message != 0 {
+            //Message might be deleted already
+            let _ = ChannelId(previous_channel).delete_message(&ctx.serenity.http, previous_message).await;
+            gate.message = 0;
+        }
+        if gate.is_enabled() {
+            if let Err(error) = self.post_gate(&ctx, &mut gate).await {
+                rogu::warn!("Unable to post gate message: {}", error);
+                let _ = ctx.reply(ctx.tr(Msg::GatePostFailed)).await;
+            }
+        }
+
+        let db = self.state.db.clone();
+        let id = guild.id.0;
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &gate)).await;
+
+        ctx.react(emoji::OK).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_check_answer() {
+        assert!(check_answer("blue", "Blue"));
+        assert!(check_answer("light blue", "  light   BLUE "));
+        assert!(!check_answer("light blue", "light"));
+        assert!(!check_answer("blue", "blue sky"));
+        assert!(!check_answer("blue", ""));
+    }
+}
@@ -0,0 +1,166 @@
+//! Goodbye of members, who left server
+
+use super::*;
+use super::args::{Arg, Args, ArgError};
+use super::welcome::{Welcome, download_avatar, encode_png};
+use crate::data::{Template, TemplateKind};
+
+use std::time;
+
+impl Handler {
+    ///Renders goodbye of user, with image if server wants it.
+    async fn render_goodbye(&self, ctx: &Context, server: GuildId, server_info: &data::Server, user: &User) -> Welcome {
+        let card = match server_info.goodbye_img {
+            true => {
+                let avatar = download_avatar(user).await;
+                let (assets, name) = (self.state.assets.clone(), user.name.clone());
+                //Decoding and drawing would stall runtime
+                tokio::task::spawn_blocking(move || encode_png(&assets.gen_goodbye(&name, avatar.as_deref()))).await.unwrap_or(None)
+            },
+            false => None,
+        };
+        let server_name = server.name(&ctx.cache).await.unwrap_or_default();
+
+        let mention = user.mention();
+        let vars: &[(&str, &dyn fmt::Display)] = &[("mention", &mention), ("name", &user.name), ("server", &server_name)];
+
+        let template = self.welcome_template(server, TemplateKind::Goodbye);
+        let text = crate::utils::render_template(template.get().unwrap_or_else(|| Msg::Goodbye.get(server_info.lang)), vars);
+
+        Welcome {
+            text,
+            card,
+        }
+    }
+
+    ///Says goodbye to member and schedules cleanup of their data.
+    pub(super) async fn handle_member_leave(&self, ctx: &Context, server: GuildId, user: &User) {
+        if user.bot {
+            return;
+        }
+
+        let server_info: data::Server = match self.state.db.get(server.0) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return;
+            }
+        };
+
+        if server_info.leave_cleanup != 0 {
+            let db = self.state.db.clone();
+            let (server_id, user_id, delay) = (server.0, user.id.0, time::Duration::from_secs(server_info.leave_cleanup));
+            let _ = tokio::task::spawn_blocking(move || scheduler::schedule_cleanup(&db, server_id, user_id, delay)).await;
+
+            let data = ctx.data.read().await;
+            if let Some(sender) = data.get::<SchedulerSendTag>() {
+                let _ = sender.send(scheduler::SchedulerCommand::Refresh).await;
+            }
+        }
+
+        //Raiders are kicked in bulk, which would flood channel
+        if server_info.goodbye_ch == 0 || self.state.db.get::<data::Raid>(server.0).map(|raid| raid.is_active).unwrap_or(false) {
+            return;
+        }
+
+        let goodbye = self.render_goodbye(ctx, server, &server_info, user).await;
+        if let Err(error) = Self::send_welcome(ctx, ChannelId(server_info.goodbye_ch), goodbye, "goodbye.png").await {
+            rogu::error!("Unable to post goodbye: {}", error);
+        }
+    }
+
+    #[inline]
+    pub async fn handle_goodbye(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let mut server = match self.state.db.get::<data::Server>(guild.id.0) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        match args.word() {
+            None => {
+                let message = self.welcome_template(guild.id, TemplateKind::Goodbye);
+                let cleanup = match server.leave_cleanup {
+                    0 => "Off".to_owned(),
+                    delay => crate::utils::format_duration(time::Duration::from_secs(delay)),
+                };
+
+                ctx.reply_embed_private(|m| {
+                    m.title("Goodbye")
+                     .field("Channel", ChannelId(server.goodbye_ch).mention(), false)
+                     .field("Image", server.goodbye_img, false)
+                     .field("Message", message.get().unwrap_or("Default"), false)
+                     .field("Cleanup", cleanup, false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("preview") => {
+                let goodbye = self.render_goodbye(ctx.serenity, guild.id, &server, ctx.author).await;
+                Self::send_welcome(ctx.serenity, ctx.channel_id, goodbye, "goodbye.png").await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("channel") => {
+                server.goodbye_ch = match try_arg!(ctx, args.next::<&str>("channel")) {
+                    "off" => 0,
+                    channel => match <ChannelId as Arg>::parse(channel) {
+                        Some(channel) if guild.channels.contains_key(&channel) => channel.0,
+                        Some(_) => return ctx.reply(ctx.tr(Msg::TargetNotFound)).await,
+                        None => return ctx.reply_usage(ArgError::Invalid("channel")).await,
+                    },
+                };
+            },
+            Some("image") => {
+                server.goodbye_img = match try_arg!(ctx, args.next::<&str>("image")) {
+                    "on" => true,
+                    "off" => false,
+                    _ => return ctx.reply_usage(ArgError::Invalid("image")).await,
+                };
+            },
+            Some("message") => {
+                let template_id = (guild.id.0, TemplateKind::Goodbye as u64);
+
+                let db = self.state.db.clone();
+                match args.rest() {
+                    "" => return ctx.reply(ctx.tr(Msg::WelcomePlaceholders)).await,
+                    "reset" => {
+                        let _ = tokio::task::spawn_blocking(move || db.delete::<Template>(template_id)).await;
+                    },
+                    template => {
+                        let template = Template::new(template);
+                        let _ = tokio::task::spawn_blocking(move || db.put(template_id, &template)).await;
+                    },
+                }
+                return ctx.react(emoji::OK).await;
+            },
+            Some("cleanup") => {
+                server.leave_cleanup = match try_arg!(ctx, args.next::<&str>("delay")) {
+                    "off" => 0,
+                    delay => match crate::utils::parse_duration(delay) {
+                        Some(delay) if delay.as_secs() > 0 => delay.as_secs(),
+                        _ => return ctx.reply_usage(ArgError::Invalid("delay")).await,
+                    },
+                };
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("setting")).await,
+        }
+
+        let db = self.state.db.clone();
+        let id = guild.id.0;
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+        ctx.react(emoji::OK).await
+    }
+}
@@ -0,0 +1,185 @@
+//! Slash commands support
+
+use super::*;
+use super::args::ArgError;
+use super::context::{respond, Source};
+use super::registry::{self, OptionKind};
+
+use serenity::model::interactions::InteractionResponseType;
+
+use serenity::model::interactions::application_command::{ApplicationCommand, ApplicationCommandInteraction, ApplicationCommandOptionType, ApplicationCommandInteractionDataOption, ApplicationCommandInteractionDataOptionValue};
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+//Discord's limit on length of descriptions
+const MAX_DESCRIPTION: usize = 100;
+
+fn short_description(description: &str) -> &str {
+    match description.char_indices().nth(MAX_DESCRIPTION) {
+        //Prefer to cut at the end of first sentence.
+        Some((idx, _)) => match description[..idx].find(". ") {
+            Some(end) => &description[..=end],
+            None => &description[..idx],
+        },
+        None => description,
+    }
+}
+
+///Registers every command, that supports it, as global slash command.
+///
+///Moderation commands are hidden, until server enables them for its moderators in integration settings.
+pub async fn register_commands(ctx: &Context) -> serenity::Result<()> {
+    ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
+        for command in registry::COMMANDS {
+            let options = match command.slash {
+                Some(options) => options,
+                None => continue,
+            };
+
+            commands.create_application_command(|slash| {
+                slash.name(command.name)
+                     .description(short_description(command.description))
+                     .default_permission(command.access == registry::Access::Everyone);
+                for option in options {
+                    let kind = match option.kind {
+                        OptionKind::Text => ApplicationCommandOptionType::String,
+                        OptionKind::Integer => ApplicationCommandOptionType::Integer,
+                        OptionKind::User => ApplicationCommandOptionType::User,
+                        OptionKind::Role => ApplicationCommandOptionType::Role,
+                        OptionKind::Channel => ApplicationCommandOptionType::Channel,
+                    };
+
+                    slash.create_option(|slash_option| {
+                        slash_option.name(option.name)
+                                    .description(option.description)
+                                    .kind(kind)
+                                    .required(option.required)
+                    });
+                }
+                slash
+            });
+        }
+
+        commands
+    }).await.map(|_| ())
+}
+
+//Converts options into the same text, as prefixed command would have.
+//
+//Fails with name of option, which text cannot be quoted.
+fn options_to_args(options: &[registry::SlashOption], values: &[ApplicationCommandInteractionDataOption]) -> Result<String, &'static str> {
+    let mut result = String::new();
+    let values: Vec<_> = options.iter().filter_map(|option| values.iter().find(|value| value.name == option.name).map(|value| (option, value))).collect();
+
+    for (idx, (option, value)) in values.iter().enumerate() {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+
+        let _ = match value.resolved.as_ref() {
+            //Only last argument may contain whitespaces without quotes
+            Some(ApplicationCommandInteractionDataOptionValue::String(text)) if idx + 1 < values.len() && (text.contains(char::is_whitespace) || text.starts_with('"')) => {
+                //Quoted word ends at the next quote, with no way to escape it
+                if text.contains('"') {
+                    return Err(option.name);
+                }
+                write!(result, "\"{}\"", text)
+            },
+            Some(ApplicationCommandInteractionDataOptionValue::String(text)) => write!(result, "{}", text),
+            Some(ApplicationCommandInteractionDataOptionValue::Integer(num)) => write!(result, "{}", num),
+            Some(ApplicationCommandInteractionDataOptionValue::User(user, _)) => write!(result, "{}", user.id.mention()),
+            Some(ApplicationCommandInteractionDataOptionValue::Role(role)) => write!(result, "{}", role.id.mention()),
+            Some(ApplicationCommandInteractionDataOptionValue::Channel(channel)) => write!(result, "{}", channel.id.mention()),
+            _ => Ok(()),
+        };
+    }
+
+    Ok(result)
+}
+
+impl Handler {
+    pub async fn handle_interaction(&self, ctx: &Context, interaction: &ApplicationCommandInteraction) -> serenity::Result<()> {
+        let (command, options) = match registry::find(&interaction.data.name) {
+            Some(command) => match command.slash {
+                Some(options) => (command, options),
+                None => return Ok(()),
+            },
+            None => {
+                rogu::warn!("Unknown slash command '{}'", interaction.data.name);
+                return Ok(());
+            }
+        };
+
+        //Discord fails interaction without response within 3 seconds, while some commands take longer
+        interaction.create_interaction_response(&ctx.http, |response| response.kind(InteractionResponseType::DeferredChannelMessageWithSource)).await?;
+
+        let (args, bad_option) = match options_to_args(options, &interaction.data.options) {
+            Ok(args) => (args, None),
+            Err(name) => (String::new(), Some(name)),
+        };
+        let is_mod = match interaction.member.as_ref() {
+            Some(member) => self.is_moderator(member.guild_id.0, member).await,
+            None => false,
+        };
+        let lang = match interaction.guild_id {
+            Some(server) => self.state.db.get::<data::Server>(server.0).map(|server| server.lang).unwrap_or_default(),
+            None => Lang::default(),
+        };
+        let replied = AtomicBool::new(false);
+
+        let context = HandlerContext {
+            serenity: ctx,
+            source: Source::Interaction(interaction, &replied),
+            author: &interaction.user,
+            guild_id: interaction.guild_id,
+            channel_id: interaction.channel_id,
+            text: "",
+            args: &args,
+            usage: command.usage,
+            lang,
+            roles: interaction.member.as_ref().map(|member| member.roles.as_slice()).unwrap_or(&[]),
+            is_mod,
+        };
+
+        if !self.is_permitted(&context, command) {
+            return context.react(emoji::KINSHI).await;
+        }
+
+        if !self.check_rate_limit(&context, command).await {
+            return Ok(());
+        }
+
+        if let Some(name) = bad_option {
+            return context.reply_usage(ArgError::Invalid(name)).await;
+        }
+
+        if command.access != registry::Access::Everyone {
+            self.log_command(&context, command).await;
+        }
+        (command.handler)(self, context).await?;
+
+        //Otherwise deferred response keeps "thinking"
+        if !replied.load(Ordering::Acquire) {
+            respond(ctx, interaction, &replied, Some(emoji::OK.to_string()), None, true).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_shorten_description() {
+        assert_eq!(short_description("Short one."), "Short one.");
+
+        let description = "Adds or removes moderator role. Once set, roles are no longer detected by name. Administrators are always moderators.";
+        assert_eq!(short_description(description), "Adds or removes moderator role.");
+
+        let description = "a".repeat(150);
+        assert_eq!(short_description(&description).len(), MAX_DESCRIPTION);
+    }
+}
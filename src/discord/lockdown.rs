@@ -0,0 +1,251 @@
+//! Channel locking and slowmode
+
+use super::*;
+use super::args::{Args, ArgError};
+use crate::data::Lock;
+
+use serenity::model::channel::{GuildChannel, PermissionOverwrite, PermissionOverwriteType};
+use serenity::model::permissions::Permissions;
+
+use std::time;
+
+//Discord's limit on slowmode
+const MAX_SLOWMODE: u64 = 6 * 60 * 60;
+
+#[inline]
+//Permissions, that are denied to @everyone in locked channel
+fn locked() -> Permissions {
+    Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS
+}
+
+impl Handler {
+    ///Denies @everyone to send messages in channel, remembering previous overwrite.
+    ///
+    ///Returns `false` if channel is locked already.
+    async fn lock_channel(&self, ctx: &Context, server: GuildId, channel: &GuildChannel) -> serenity::Result<bool> {
+        let key = (server.0, channel.id.0);
+        if self.state.db.get::<Lock>(key).map(|lock| lock.is_locked).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        //@everyone role has the same id as server
+        let everyone = PermissionOverwriteType::Role(RoleId(server.0));
+        let previous = channel.permission_overwrites.iter().find(|overwrite| overwrite.kind == everyone);
+        let lock = Lock {
+            is_locked: true,
+            had_overwrite: previous.is_some(),
+            allow: previous.map(|overwrite| overwrite.allow.bits()).unwrap_or(0),
+            deny: previous.map(|overwrite| overwrite.deny.bits()).unwrap_or(0),
+        };
+        let overwrite = PermissionOverwrite {
+            allow: Permissions::from_bits_truncate(lock.allow) - locked(),
+            deny: Permissions::from_bits_truncate(lock.deny) | locked(),
+            kind: everyone,
+        };
+
+        //Store it first, so that channel can be restored even if bot is interrupted
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(key, &lock)).await;
+
+        if let Err(error) = channel.id.create_permission(&ctx.http, &overwrite).await {
+            self.state.db.delete::<Lock>(key);
+            return Err(error);
+        }
+
+        Ok(true)
+    }
+
+    ///Restores @everyone overwrite, that channel had before lock.
+    ///
+    ///Returns `false` if channel is not locked.
+    async fn unlock_channel(&self, ctx: &Context, server: GuildId, channel: ChannelId) -> serenity::Result<bool> {
+        let key = (server.0, channel.0);
+        let lock = match self.state.db.get::<Lock>(key) {
+            Ok(lock) if lock.is_locked => lock,
+            _ => return Ok(false),
+        };
+
+        let everyone = PermissionOverwriteType::Role(RoleId(server.0));
+        match lock.had_overwrite {
+            true => channel.create_permission(&ctx.http, &PermissionOverwrite {
+                allow: Permissions::from_bits_truncate(lock.allow),
+                deny: Permissions::from_bits_truncate(lock.deny),
+                kind: everyone,
+            }).await?,
+            false => channel.delete_permission(&ctx.http, everyone).await?,
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.delete::<Lock>(key)).await;
+
+        Ok(true)
+    }
+
+    ///Forgets lock and lockdown membership of deleted channel.
+    pub(super) async fn forget_lock(&self, channel: &GuildChannel) {
+        let db = self.state.db.clone();
+        let (server, id) = (channel.guild_id.0, channel.id.0);
+        let _ = tokio::task::spawn_blocking(move || {
+            db.delete::<Lock>((server, id));
+            db.delete::<data::Channel>(id);
+        }).await;
+    }
+
+    #[inline]
+    pub async fn handle_lock(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let channel = try_arg!(ctx, Args::new(ctx.args).optional::<ChannelId>("channel")).unwrap_or(ctx.channel_id);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        let channel = match guild.channels.get(&channel) {
+            Some(channel) => channel,
+            None => return ctx.reply(ctx.tr(Msg::TargetNotFound)).await,
+        };
+
+        match self.lock_channel(ctx.serenity, guild.id, channel).await {
+            Ok(true) => ctx.react(emoji::OK).await,
+            Ok(false) => ctx.reply(ctx.tr(Msg::AlreadyLocked)).await,
+            Err(error) => {
+                rogu::warn!("Unable to lock channel {}: {}", channel.id.0, error);
+                ctx.reply(ctx.tr(Msg::LockFailed)).await
+            }
+        }
+    }
+
+    #[inline]
+    pub async fn handle_unlock(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let channel = try_arg!(ctx, Args::new(ctx.args).optional::<ChannelId>("channel")).unwrap_or(ctx.channel_id);
+
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        match self.unlock_channel(ctx.serenity, server, channel).await {
+            Ok(true) => ctx.react(emoji::OK).await,
+            Ok(false) => ctx.reply(ctx.tr(Msg::NotLocked)).await,
+            Err(error) => {
+                rogu::warn!("Unable to unlock channel {}: {}", channel.0, error);
+                ctx.reply(ctx.tr(Msg::LockFailed)).await
+            }
+        }
+    }
+
+    #[inline]
+    pub async fn handle_lockdown(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let is_lock = match args.word() {
+            None => true,
+            Some("lift") => false,
+            Some(action @ "add") | Some(action @ "remove") => {
+                let channel = try_arg!(ctx, args.next::<ChannelId>("channel"));
+                if !guild.channels.contains_key(&channel) {
+                    return ctx.reply(ctx.tr(Msg::TargetNotFound)).await;
+                }
+
+                let mut channel_info = match self.state.db.get::<data::Channel>(channel.0) {
+                    Ok(channel_info) => channel_info,
+                    Err(error) => {
+                        rogu::error!("Unable to get channel info: {}", error);
+                        let _ = ctx.react(emoji::KINSHI).await;
+                        return Ok(());
+                    }
+                };
+                channel_info.is_lockdown = action == "add";
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(channel.0, &channel_info)).await;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("list") => {
+                let mut text = String::new();
+                for channel in guild.channels.keys().filter(|channel| self.state.db.get::<data::Channel>(channel.0).map(|info| info.is_lockdown).unwrap_or(false)) {
+                    text.push_str(&format!("- {}\n", channel.mention()));
+                }
+
+                if text.is_empty() {
+                    return ctx.reply(ctx.tr(Msg::NoLockdownChannels)).await;
+                }
+                ctx.reply_embed_private(|m| m.title("Lockdown channels").description(text)).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        };
+
+        let channels: Vec<_> = guild.channels.values().filter(|channel| self.state.db.get::<data::Channel>(channel.id.0).map(|info| info.is_lockdown).unwrap_or(false)).collect();
+        if channels.is_empty() {
+            return ctx.reply(ctx.tr(Msg::NoLockdownChannels)).await;
+        }
+
+        let mut count = 0;
+        for channel in channels {
+            let result = match is_lock {
+                true => self.lock_channel(ctx.serenity, guild.id, channel).await,
+                false => self.unlock_channel(ctx.serenity, guild.id, channel.id).await,
+            };
+
+            match result {
+                Ok(true) => count += 1,
+                //Channel has been locked or unlocked separately
+                Ok(false) => (),
+                Err(error) => rogu::warn!("Unable to change lock of channel {}: {}", channel.id.0, error),
+            }
+        }
+
+        match is_lock {
+            true => ctx.reply(ctx.tr_with(Msg::LockedDown, &[("count", &count)])).await,
+            false => ctx.reply(ctx.tr_with(Msg::LockdownLifted, &[("count", &count)])).await,
+        }
+    }
+
+    #[inline]
+    pub async fn handle_slowmode(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let rate = match try_arg!(ctx, args.next::<&str>("duration")) {
+            "off" => 0,
+            duration => match crate::utils::parse_duration(duration).map(|duration: time::Duration| duration.as_secs()) {
+                Some(rate) if rate <= MAX_SLOWMODE => rate,
+                Some(_) => return ctx.reply(ctx.tr_with(Msg::BadSlowmode, &[("max", &crate::utils::format_duration(time::Duration::from_secs(MAX_SLOWMODE)))])).await,
+                None => return ctx.reply_usage(ArgError::Invalid("duration")).await,
+            },
+        };
+        let channel = try_arg!(ctx, args.optional::<ChannelId>("channel")).unwrap_or(ctx.channel_id);
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        //Bot may manage channels of other servers
+        if !guild.channels.contains_key(&channel) {
+            return ctx.reply(ctx.tr(Msg::TargetNotFound)).await;
+        }
+
+        match channel.edit(&ctx.serenity.http, |edit| edit.slow_mode_rate(rate)).await {
+            Ok(_) => ctx.react(emoji::OK).await,
+            Err(error) => {
+                rogu::warn!("Unable to set slowmode of channel {}: {}", channel.0, error);
+                ctx.reply(ctx.tr(Msg::LockFailed)).await
+            }
+        }
+    }
+}
@@ -3,18 +3,21 @@ use serenity::client::Context;
 use serenity::model::misc::Mentionable;
 use serenity::prelude::{TypeMapKey};
 use serenity::client::bridge::gateway::{ShardManager};
-use serenity::model::prelude::{Ready, Message, Guild, GuildUnavailable, GuildId, Member, ChannelId, PartialGuild, RoleId, Role};
+use serenity::model::prelude::{Ready, Message, MessageId, Guild, GuildUnavailable, GuildId, Member, ChannelId, PartialGuild, RoleId, Role, User, UserId, VoiceState};
+use serenity::model::event::MessageUpdateEvent;
+use serenity::model::interactions::Interaction;
+use serenity::model::channel::{GuildChannel, Reaction};
 
 use crate::{game, data};
+use crate::lang::{Lang, Msg};
 use crate::assets::Assets;
 use crate::db::DbView;
-use crate::utils::OptionExt;
 
 use core::fmt;
 use std::collections::{HashMap, HashSet};
 
-const CMD_HELP_TXT: &str = include_str!("../../HELP.md");
-const MOD_CMD_HELP_TXT: &str = include_str!("../../MOD_HELP.md");
+//Number of messages per channel to keep in cache
+const MAX_CACHED_MESSAGES: usize = 200;
 
 struct ShardManagerTag;
 impl TypeMapKey for ShardManagerTag {
@@ -26,14 +29,38 @@ impl TypeMapKey for PlayerSendTag {
     type Value = player::PlayerSender;
 }
 
+struct SchedulerSendTag;
+impl TypeMapKey for SchedulerSendTag {
+    type Value = scheduler::SchedulerSender;
+}
+
 mod utils;
+#[macro_use]
+mod args;
+mod context;
 mod commands;
 mod emoji;
 mod player;
+mod voice;
+mod permission;
+mod registry;
+mod interaction;
+mod ratelimit;
+mod moderation;
+mod scheduler;
+mod modlog;
+mod automod;
+mod raid;
+mod lockdown;
+mod reactrole;
+mod gate;
+mod welcome;
+mod goodbye;
 
 #[derive(Clone)]
 pub struct Config {
-    prefix: char,
+    //Default prefix, used when server has none
+    prefix: crate::cli::PrefixStr,
 }
 
 #[derive(Clone)]
@@ -46,20 +73,49 @@ struct Info {
 struct State {
     info: Info,
     db: DbView,
-    assets: Assets,
+    assets: std::sync::Arc<Assets>,
+}
+
+#[derive(Default)]
+//Per server information to determine moderators
+struct Mods {
+    owner: u64,
+    roles: HashSet<u64>,
 }
 
 //Discord state and handler, which processes incoming messages.
 struct Handler {
     state: State,
-    mods: tokio::sync::RwLock<HashSet<u64>>,
+    mods: tokio::sync::RwLock<HashMap<u64, Mods>>,
+    voice: voice::VoiceSessions,
+    //Whether ongoing voice sessions are credited periodically
+    is_voice_ticking: core::sync::atomic::AtomicBool,
+    //Slash commands are global, so they are registered once per process
+    is_registered: core::sync::atomic::AtomicBool,
+    limits: ratelimit::RateLimiter,
+    //Compiled blocklists per server
+    blocklists: tokio::sync::RwLock<HashMap<u64, std::sync::Arc<automod::Blocklist>>>,
+    spam: automod::SpamTracker,
+    own_deletions: modlog::OwnDeletions,
+    joins: raid::JoinTracker,
     config: Config,
 }
 
 pub struct HandlerContext<'a> {
     serenity: &'a Context,
-    msg: &'a Message,
+    source: context::Source<'a>,
+    author: &'a User,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
     text: &'a str,
+    //Text after command's name
+    args: &'a str,
+    //Usage of executed command, used to report bad arguments
+    usage: &'static str,
+    //Language of server, English outside of server
+    lang: Lang,
+    //Roles of author, empty outside of server
+    roles: &'a [RoleId],
     is_mod: bool,
 }
 
@@ -76,61 +132,146 @@ impl<'a> serenity::http::CacheHttp for HandlerContext<'a> {
 }
 
 impl Handler {
-    async fn update_mod_roles(&self, roles: &HashMap<RoleId, Role>) {
-        let mut mods = self.mods.write().await;
+    async fn update_mod_roles(&self, server_id: u64, owner: UserId, roles: &HashMap<RoleId, Role>) {
+        use serenity::model::permissions::Permissions;
+
+        let mod_roles = match self.state.db.get::<data::Server>(server_id) {
+            Ok(server) => server.mod_roles,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                Default::default()
+            }
+        };
+        //Name matching is only used until roles are configured explicitly
+        let is_configured = mod_roles.iter().any(|role| *role != 0);
+
+        let mut result = Mods {
+            owner: owner.0,
+            roles: HashSet::new(),
+        };
         for (id, role) in roles {
-            if role.name.eq_ignore_ascii_case("Moderator") || role.name.contains("Staff") {
-                mods.insert(id.0);
+            let is_mod = if role.permissions.intersects(Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD) {
+                true
+            } else if is_configured {
+                mod_roles.contains(&id.0)
+            } else {
+                role.name.eq_ignore_ascii_case("Moderator") || role.name.contains("Staff")
+            };
+
+            if is_mod {
+                result.roles.insert(id.0);
             }
         }
+
+        self.mods.write().await.insert(server_id, result);
     }
 
-    async fn is_moderator(&self, roles: &[RoleId]) -> bool {
-        let mods = self.mods.read().await;
-        for role in roles {
-            if mods.contains(&role.0) {
-                return true;
-            }
+    async fn refresh_mod_roles(&self, ctx: &Context, server: GuildId) {
+        match ctx.cache.guild(server).await {
+            Some(guild) => self.update_mod_roles(server.0, guild.owner_id, &guild.roles).await,
+            None => rogu::warn!("Server {} is not cached, cannot refresh moderators", server.0),
         }
+    }
 
-        false
+    async fn is_moderator(&self, server_id: u64, member: &Member) -> bool {
+        self.is_moderator_by_roles(server_id, member.user.id, &member.roles).await
     }
 
-    async fn handle_chat(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        struct LevelUpCong(String, u8);
+    async fn is_moderator_by_roles(&self, server_id: u64, user: UserId, roles: &[RoleId]) -> bool {
+        let mods = self.mods.read().await;
+        match mods.get(&server_id) {
+            Some(mods) => mods.owner == user.0 || roles.iter().any(|role| mods.roles.contains(&role.0)),
+            None => false,
+        }
+    }
 
-        impl fmt::Display for LevelUpCong {
-            #[inline]
-            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(f, "{} Congratulations on level up! Your new level is {}", self.0, self.1)
+    async fn announce_level_up(&self, ctx: &Context, server_id: u64, channel: Option<ChannelId>, user: UserId, level: u8) -> serenity::Result<()> {
+        let server_info: data::Server = match self.state.db.get(server_id) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return Ok(());
             }
-        }
+        };
 
-        let id = ctx.msg.author.id.0;
-        let mut user: data::User = match self.state.db.get(id) {
-            Ok(user) => user,
+        let spam_ch = match server_info.spam_ch {
+            0 => None,
+            spam_ch => Some(ChannelId(spam_ch)),
+        };
+        let channel = match server_info.level_up_dest {
+            data::LevelUpDest::Off => return Ok(()),
+            data::LevelUpDest::Spam => spam_ch,
+            data::LevelUpDest::Channel => channel.or(spam_ch),
+            data::LevelUpDest::Dm => Some(user.create_dm_channel(ctx).await?.id),
+        };
+
+        let channel = match channel {
+            Some(channel) => channel,
+            None => return Ok(()),
+        };
+
+        let template = match self.state.db.get::<data::Template>((server_id, data::TemplateKind::LevelUp as u64)) {
+            Ok(template) => template,
             Err(error) => {
-                rogu::error!("Unable to get user data: {}", error);
-                return Ok(())
+                rogu::error!("Unable to get level up template: {}", error);
+                data::Template::default()
             }
         };
+        let template = template.get().unwrap_or_else(|| Msg::LevelUp.get(server_info.lang));
+
+        let rank = match template.contains("{rank}") {
+            true => {
+                //Rank among server's members, as announcement is per server
+                let members: Vec<u64> = ctx.cache.guild(server_id).await.map(|guild| guild.members.keys().map(|member| member.0).collect()).unwrap_or_default();
+                let db = self.state.db.clone();
+                tokio::task::spawn_blocking(move || {
+                    let exp = db.get::<data::User>(user.0).map(|user| user.exp).unwrap_or(0);
+                    members.iter().filter(|member| db.get::<data::User>(**member).map(|member| member.exp > exp).unwrap_or(false)).count() + 1
+                }).await.unwrap_or(1)
+            },
+            false => 0,
+        };
+        let text = {
+            let mention = user.mention();
+            crate::utils::render_template(template, &[("mention", &mention), ("level", &level), ("rank", &rank)])
+        };
 
-        let mut level = game::Level::new(user.exp);
-        let result = level.add_for(ctx.msg);
-        if result == game::LevelAddResult::Maxed {
-            return Ok(())
+        if server_info.level_up_img {
+            let user = user.to_user(ctx).await?;
+            let assets = self.state.assets.clone();
+            //Drawing would stall runtime
+            let img = tokio::task::spawn_blocking(move || welcome::encode_png(&assets.gen_level_up(&user.name, level))).await.unwrap_or(None);
+
+            if let Some(buffer) = img {
+                let attach = serenity::http::AttachmentType::Bytes {
+                    data: buffer.as_slice().into(),
+                    filename: "level_up.png".to_owned(),
+                };
+
+                return channel.send_files(&ctx.http, Some(attach), |msg| msg.content(text)).await.map(|_| ());
+            }
         }
 
+        channel.send_message(ctx, |msg| msg.content(text)).await.map(|_| ())
+    }
 
-        user.exp = level.exp;
-        let db = self.state.db.clone();
-        let _ = tokio::task::spawn_blocking(move || db.put(id, &user)).await;
+    async fn handle_chat(&self, ctx: &Context, msg: &Message) -> serenity::Result<()> {
+        use game::LevelExpModifier;
 
-        let server_id = match ctx.msg.guild_id {
+        let server_id = match msg.guild_id {
             Some(server) => server.0,
             None => return Ok(()),
         };
 
+        let id = msg.author.id.0;
+        let mut user: data::User = match self.state.db.get(id) {
+            Ok(user) => user,
+            Err(error) => {
+                rogu::error!("Unable to get user data: {}", error);
+                return Ok(())
+            }
+        };
+
         let server_info: data::Server = match self.state.db.get(server_id) {
             Ok(server_info) => server_info,
             Err(error) => {
@@ -139,44 +280,81 @@ impl Handler {
             }
         };
 
-        if server_info.spam_ch == 0 {
-            return Ok(());
+        let channel_info: data::Channel = match self.state.db.get(msg.channel_id.0) {
+            Ok(channel_info) => channel_info,
+            Err(error) => {
+                rogu::error!("Unable to get channel info: {}", error);
+                return Ok(());
+            }
+        };
+
+        let now = std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let rate = game::ExpRate(channel_info.exp_weight).compose(game::ExpRate(server_info.exp_event_rate(now)));
+
+        let mut level = game::Level::new(user.exp);
+        let exp = rate.apply(msg.calculate(level.level));
+        if exp == 0 {
+            return Ok(())
+        }
+
+        let result = level.add(exp);
+        if result == game::LevelAddResult::Maxed {
+            return Ok(())
         }
 
+
+        user.exp = level.exp;
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &user)).await;
+
         if result == game::LevelAddResult::LevelUp {
-            let author = ctx.msg.author.mention();
-            let level = LevelUpCong(author.to_string(), level.level);
-            ChannelId(server_info.spam_ch).send_message(&ctx.serenity, |msg| msg.content(level)).await.map(|_| ())
+            self.announce_level_up(ctx, server_id, Some(msg.channel_id), msg.author.id, level.level).await
         } else {
             Ok(())
         }
     }
 
-    async fn handle_cmd(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
-        use commands::*;
-
-        let mut split = ctx.text.split_whitespace();
-        //First iteration always has some value, even if there are no whitespaces;
-        let cmd = split.next().unwrap_certain();
-
-        match xxhash_rust::xxh3::xxh3_64(cmd.as_bytes()) {
-            PING => ctx.msg.reply(ctx, "pong!").await.map(|_| ()),
-            HELP => self.handle_help(ctx).await,
-            ROLL => self.handle_roll(ctx).await,
-            JUDGE => self.handle_judge(ctx, split.collect()).await,
-            PLAYER => self.handle_player(ctx, split).await,
-            SUGGEST => self.handle_suggest(ctx).await,
-            WHOAMI => self.handle_whoami(ctx).await,
-            ALLOWANCE => self.handle_allowance(ctx).await,
-            SHUTDOWN => self.handle_shutdown(ctx).await,
-            RESTART => self.handle_restart(ctx).await,
-            CONFIG => self.handle_config(ctx).await,
-            SET_WELCOME => self.handle_set_welcome(ctx).await,
-            SET_VOICE => self.handle_set_voice(ctx).await,
-            SET_DEV => self.handle_set_dev(ctx).await,
-            SET_SPAM => self.handle_set_spam(ctx).await,
-            _ => ctx.msg.reply(ctx, "Sorry, I do not know such command").await.map(|_| ()),
+    async fn handle_cmd(&self, mut ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        struct Unknown(Option<&'static registry::Command>, Lang);
+        impl fmt::Display for Unknown {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(Msg::UnknownCommand.get(self.1))?;
+                match self.0 {
+                    Some(command) => f.write_str(&crate::utils::render_template(Msg::DidYouMean.get(self.1), &[("command", &command.name)])),
+                    None => Ok(()),
+                }
+            }
+        }
+
+        let (cmd, args) = match ctx.text.find(char::is_whitespace) {
+            Some(idx) => (&ctx.text[..idx], ctx.text[idx..].trim_start()),
+            None => (ctx.text, ""),
+        };
+
+        if cmd.is_empty() {
+            return Ok(());
+        }
+
+        let command = match registry::find(cmd) {
+            Some(command) => command,
+            None => return ctx.reply(Unknown(registry::suggest(cmd), ctx.lang)).await,
+        };
+
+        if !self.is_permitted(&ctx, command) {
+            let _ = ctx.react(emoji::KINSHI).await;
+            return Ok(());
+        }
+
+        if !self.check_rate_limit(&ctx, command).await {
+            return Ok(());
+        }
+
+        ctx.args = args;
+        ctx.usage = command.usage;
+        if command.access != registry::Access::Everyone {
+            self.log_command(&ctx, command).await;
         }
+        (command.handler)(self, ctx).await
     }
 }
 
@@ -206,10 +384,10 @@ impl Discord {
             state: State {
                 info,
                 db,
-                assets,
+                assets: std::sync::Arc::new(assets),
             },
             config: Config {
-                prefix: args.prefix,
+                prefix: args.prefix.0,
             },
             token: args.token.0,
         })
@@ -219,11 +397,19 @@ impl Discord {
         loop {
             let handler = Handler {
                 state: self.state.clone(),
-                mods: tokio::sync::RwLock::new(HashSet::new()),
+                mods: tokio::sync::RwLock::new(HashMap::new()),
+                voice: tokio::sync::Mutex::new(HashMap::new()),
+                is_voice_ticking: core::sync::atomic::AtomicBool::new(false),
+                is_registered: core::sync::atomic::AtomicBool::new(false),
+                limits: ratelimit::RateLimiter::default(),
+                blocklists: tokio::sync::RwLock::new(HashMap::new()),
+                spam: automod::SpamTracker::default(),
+                own_deletions: modlog::OwnDeletions::default(),
+                joins: raid::JoinTracker::default(),
                 config: self.config.clone(),
             };
 
-            let client = serenity::client::Client::builder(self.token.as_str());
+            let client = serenity::client::Client::builder(self.token.as_str()).application_id(self.state.info.id);
 
             let voice_manager = songbird::Songbird::serenity();
             let client = songbird::serenity::register_with(client, voice_manager.clone());
@@ -237,19 +423,27 @@ impl Discord {
             };
 
             let (player, sender) = player::MusicPlayer::new(self.state.db.clone(), voice_manager);
+            let (scheduler, scheduler_sender) = scheduler::Scheduler::new(self.state.db.clone(), client.cache_and_http.http.clone(), self.state.info.id);
             {
                 let mut data = client.data.write().await;
                 data.insert::<ShardManagerTag>(client.shard_manager.clone());
                 data.insert::<PlayerSendTag>(sender.clone());
+                data.insert::<SchedulerSendTag>(scheduler_sender.clone());
             }
 
+            //Deleted and edited messages can be shown in mod-log only if they are cached
+            client.cache_and_http.cache.set_max_messages(MAX_CACHED_MESSAGES).await;
+
             tokio::spawn(player.run());
+            //Pending expirations are stored in db, so restart only needs a new scheduler.
+            tokio::spawn(scheduler.run());
 
             loop {
                 if let Err(error) = client.start().await {
                     rogu::error!("Client failure. Error: {}", error);
                 } else {
                     rogu::info!("Shutting down");
+                    let _ = scheduler_sender.send(scheduler::SchedulerCommand::Shutdown).await;
                     return;
                 }
             }
@@ -267,51 +461,68 @@ impl serenity::client::EventHandler for Handler {
         }
 
         let content = msg.content.trim();
+        let server_info = msg.guild_id.and_then(|server| self.state.db.get::<data::Server>(server.0).ok());
+        let prefix = server_info.as_ref().and_then(data::Server::prefix).unwrap_or(self.config.prefix.as_str());
+        let cmd = utils::strip_command(content, prefix, self.state.info.id);
 
-        let result = if msg.guild_id.is_none() {
-            let trimmed_cmd = content.trim_start_matches(self.config.prefix);
-            //Handle only commands in DM
-            if content.len() - trimmed_cmd.len() != 1 {
+        //Commands are filtered too, otherwise prefix would bypass filters
+        if let Some(server_info) = server_info.as_ref() {
+            if self.auto_moderate(&ctx, &msg, server_info).await {
                 return;
             }
+        }
+
+        let result = if msg.guild_id.is_none() {
+            //Handle only commands in DM
+            let cmd = match cmd {
+                Some(cmd) => cmd,
+                None => return,
+            };
 
             let context = HandlerContext {
                 serenity: &ctx,
-                msg: &msg,
-                text: trimmed_cmd,
+                source: context::Source::Message(&msg),
+                author: &msg.author,
+                guild_id: msg.guild_id,
+                channel_id: msg.channel_id,
+                text: cmd,
+                args: "",
+                usage: "",
+                lang: Lang::default(),
+                roles: &[],
                 is_mod: false,
             };
             self.handle_cmd(context).await
-        } else {
-            let trimmed_cmd = content.trim_start_matches(self.config.prefix);
-
-            if content.len() - trimmed_cmd.len() != 1 {
-                let context = HandlerContext {
-                    serenity: &ctx,
-                    msg: &msg,
-                    text: content,
-                    //we do not care if chat is from moderator or not, at least for now
-                    is_mod: false,
-                };
-                self.handle_chat(context).await
-            } else {
-                let is_mod = match msg.member(&ctx.http).await {
-                    Ok(member) => self.is_moderator(&member.roles).await,
-                    Err(error) => {
-                        //It is supposed to fail when message is DM, but we handle DM differently
-                        rogu::warn!("Member info is unavailable, cannot determine moderator status. Error: {}", error);
-                        false
-                    }
-                };
+        } else if let Some(cmd) = cmd {
+            let member = match msg.member(&ctx.http).await {
+                Ok(member) => Some(member),
+                Err(error) => {
+                    //It is supposed to fail when message is DM, but we handle DM differently
+                    rogu::warn!("Member info is unavailable, cannot determine moderator status. Error: {}", error);
+                    None
+                }
+            };
+            let is_mod = match member.as_ref() {
+                Some(member) => self.is_moderator(member.guild_id.0, member).await,
+                None => false,
+            };
 
-                let context = HandlerContext {
-                    serenity: &ctx,
-                    msg: &msg,
-                    text: trimmed_cmd,
-                    is_mod,
-                };
-                self.handle_cmd(context).await
-            }
+            let context = HandlerContext {
+                serenity: &ctx,
+                source: context::Source::Message(&msg),
+                author: &msg.author,
+                guild_id: msg.guild_id,
+                channel_id: msg.channel_id,
+                text: cmd,
+                args: "",
+                usage: "",
+                lang: server_info.as_ref().map(|server| server.lang).unwrap_or_default(),
+                roles: member.as_ref().map(|member| member.roles.as_slice()).unwrap_or(&[]),
+                is_mod,
+            };
+            self.handle_cmd(context).await
+        } else {
+            self.handle_chat(&ctx, &msg).await
         };
 
         if let Err(error) = result {
@@ -331,66 +542,147 @@ impl serenity::client::EventHandler for Handler {
                 }
             };
 
-            self.update_mod_roles(&info.roles).await;
+            self.update_mod_roles(server.0, info.owner_id, &info.roles).await;
+            //Pick up members that are already in voice
+            self.update_voice_activity(&ctx, server).await;
+        }
+
+        //Each event is handled in its own task, so it can keep ticking for the lifetime of client
+        if !self.is_voice_ticking.swap(true, core::sync::atomic::Ordering::AcqRel) {
+            let mut interval = tokio::time::interval(voice::VOICE_TICK);
+            loop {
+                interval.tick().await;
+                self.credit_voice_activity(&ctx).await;
+            }
         }
     }
 
     async fn guild_update(&self, _: Context, _: Option<Guild>, update: PartialGuild) {
-        self.update_mod_roles(&update.roles).await;
+        self.update_mod_roles(update.id.0, update.owner_id, &update.roles).await;
     }
 
-    async fn guild_delete(&self, _: Context, server: GuildUnavailable, _: Option<Guild>) {
-        self.state.db.delete::<data::Server>(server.id.0);
+    async fn guild_role_create(&self, ctx: Context, server: GuildId, _: Role) {
+        self.refresh_mod_roles(&ctx, server).await;
     }
 
-    async fn guild_member_addition(&self, ctx: Context, server: GuildId, member: Member) {
-        if member.user.bot {
+    async fn guild_role_update(&self, ctx: Context, server: GuildId, _: Option<Role>, _: Role) {
+        self.refresh_mod_roles(&ctx, server).await;
+    }
+
+    async fn guild_role_delete(&self, ctx: Context, server: GuildId, _: RoleId, _: Option<Role>) {
+        self.refresh_mod_roles(&ctx, server).await;
+    }
+
+    async fn guild_delete(&self, _: Context, server: GuildUnavailable, _: Option<Guild>) {
+        //Outage is not removal, so configuration is kept
+        if server.unavailable {
             return;
         }
 
-        let server_id = server.0;
-        let server_info: data::Server = match self.state.db.get(server_id) {
-            Ok(server_info) => server_info,
-            Err(error) => {
-                rogu::error!("Unable to get server info: {}", error);
-                return;
+        self.mods.write().await.remove(&server.id.0);
+        self.blocklists.write().await.remove(&server.id.0);
+
+        let db = self.state.db.clone();
+        let id = server.id.0;
+        let _ = tokio::task::spawn_blocking(move || db.delete_server(id)).await;
+    }
+
+    async fn channel_delete(&self, _: Context, channel: &GuildChannel) {
+        self.forget_lock(channel).await;
+    }
+
+    async fn message_update(&self, ctx: Context, old: Option<Message>, _: Option<Message>, event: MessageUpdateEvent) {
+        self.log_message_update(&ctx, old, &event).await;
+    }
+
+    async fn message_delete(&self, ctx: Context, channel: ChannelId, id: MessageId, server: Option<GuildId>) {
+        if let Some(server) = server {
+            self.log_message_delete(&ctx, server, channel, id).await;
+            self.forget_reaction_roles(server, id).await;
+        }
+    }
+
+    async fn message_delete_bulk(&self, ctx: Context, channel: ChannelId, ids: Vec<MessageId>, server: Option<GuildId>) {
+        if let Some(server) = server {
+            self.log_message_delete_bulk(&ctx, server, channel, &ids).await;
+            for id in ids {
+                self.forget_reaction_roles(server, id).await;
             }
-        };
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        self.handle_reaction_role(&ctx, &reaction, true).await;
+        self.handle_gate_reaction(&ctx, &reaction).await;
+    }
+
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        self.handle_reaction_role(&ctx, &reaction, false).await;
+    }
+
+    async fn guild_member_update(&self, ctx: Context, old: Option<Member>, new: Member) {
+        self.log_member_update(&ctx, old.as_ref(), &new).await;
+    }
+
+    async fn guild_member_removal(&self, ctx: Context, server: GuildId, user: User, member: Option<Member>) {
+        self.log_member_leave(&ctx, server, &user, member.as_ref()).await;
+        self.handle_member_leave(&ctx, server, &user).await;
+    }
 
-        if server_info.welcome_ch == 0 {
+    async fn guild_ban_addition(&self, ctx: Context, server: GuildId, user: User) {
+        self.log_ban(&ctx, server, &user, true).await;
+    }
+
+    async fn guild_ban_removal(&self, ctx: Context, server: GuildId, user: User) {
+        self.log_ban(&ctx, server, &user, false).await;
+    }
+
+    async fn guild_member_addition(&self, ctx: Context, server: GuildId, member: Member) {
+        self.log_member_join(&ctx, &member).await;
+
+        if member.user.bot {
             return;
         }
 
-        let name = member.user.name.as_str();
-        let mention = member.user.mention();
+        //Data of returning member is kept
+        let db = self.state.db.clone();
+        let (server_id, user_id) = (server.0, member.user.id.0);
+        let _ = tokio::task::spawn_blocking(move || scheduler::cancel_cleanup(&db, server_id, user_id)).await;
 
-        let welcome_ch = ChannelId(server_info.welcome_ch);
-        let result = match self.state.assets.gen_welcome(name) {
-            Some(img) => {
-                let mut buffer = Vec::new();
-                if let Err(error) = img.write_to(&mut buffer, image::ImageOutputFormat::Png) {
-                    rogu::error!("Unexpected error generating image: {}", error);
-                    return;
-                }
+        let raid = self.check_raid(&ctx, &member).await;
+        if raid == Some(data::RaidAction::Kick) {
+            return;
+        }
 
-                let attach = serenity::http::AttachmentType::Bytes {
-                    data: buffer.as_slice().into(),
-                    filename: "welcome.png".to_owned(),
-                };
+        //Welcome is postponed until verification
+        if !self.handle_gate_join(&ctx, &member, raid.is_some()).await {
+            self.welcome_member(&ctx, server, &member.user, raid.is_some()).await;
+        }
+    }
 
-                welcome_ch.send_files(&ctx.http, Some(attach), |msg| msg.content(format_args!("{}", mention))).await
-            },
-            None => welcome_ch.send_message(&ctx.http, |msg| {
-                msg.content(format_args!("{}: Welcome to the server!", mention))
-            }).await
-        };
+    async fn voice_state_update(&self, ctx: Context, server: Option<GuildId>, _: Option<VoiceState>, _: VoiceState) {
+        if let Some(server) = server {
+            self.update_voice_activity(&ctx, server).await;
+        }
+    }
 
-        if let Err(error) = result {
-            rogu::error!("Unable to post welcome: {}", error);
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::ApplicationCommand(command) = interaction {
+            if let Err(error) = self.handle_interaction(&ctx, &command).await {
+                rogu::error!("Failed to respond to slash command: {}", error);
+            }
         }
     }
 
-    async fn ready(&self, _: Context, _: Ready) {
+    async fn ready(&self, ctx: Context, _: Ready) {
         rogu::debug!("Connected");
+
+        //Reconnect fires ready again
+        if !self.is_registered.swap(true, core::sync::atomic::Ordering::AcqRel) {
+            if let Err(error) = interaction::register_commands(&ctx).await {
+                rogu::error!("Unable to register slash commands: {}", error);
+                self.is_registered.store(false, core::sync::atomic::Ordering::Release);
+            }
+        }
     }
 }
@@ -0,0 +1,468 @@
+//! Moderation actions and their history
+
+use super::*;
+use super::args::{Arg, Args, ArgError};
+use super::context::Source;
+use crate::data::{Case, CaseAction, Expiry};
+use crate::db::DbView;
+
+use std::time;
+
+//Maximum number of cases to list at once
+const MAX_CASES: usize = 20;
+//Maximum number of messages to purge at once
+const MAX_PURGE: usize = 1000;
+//Maximum number of messages to look through, when looking for matching ones
+const MAX_PURGE_SCAN: usize = 5000;
+//Discord's limit on bulk delete
+const MAX_BULK_DELETE: usize = 100;
+//Discord refuses to bulk delete messages older than 14 days, keep some margin
+const MAX_BULK_DELETE_AGE: u64 = 14 * 24 * 60 * 60 - 60 * 60;
+
+#[derive(Debug, PartialEq)]
+enum PurgeFilter {
+    All,
+    User(UserId),
+    Bots,
+    //Lowercase text
+    Contains(String),
+    Attachments,
+}
+
+impl PurgeFilter {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "" => Some(PurgeFilter::All),
+            "bots" => Some(PurgeFilter::Bots),
+            "attachments" => Some(PurgeFilter::Attachments),
+            text => match text.strip_prefix("contains:").map(str::trim) {
+                Some("") => None,
+                Some(text) => Some(PurgeFilter::Contains(text.to_lowercase())),
+                None => UserId::parse(text).map(PurgeFilter::User),
+            },
+        }
+    }
+
+    fn matches(&self, msg: &Message) -> bool {
+        match self {
+            PurgeFilter::All => true,
+            PurgeFilter::User(user) => msg.author.id == *user,
+            PurgeFilter::Bots => msg.author.bot,
+            PurgeFilter::Contains(text) => msg.content.to_lowercase().contains(text.as_str()),
+            PurgeFilter::Attachments => !msg.attachments.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for PurgeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PurgeFilter::All => f.write_str("all"),
+            PurgeFilter::User(user) => write!(f, "{}", user.mention()),
+            PurgeFilter::Bots => f.write_str("bots"),
+            PurgeFilter::Contains(text) => write!(f, "contains `{}`", text),
+            PurgeFilter::Attachments => f.write_str("attachments"),
+        }
+    }
+}
+
+struct CaseEntry<'a>(u64, &'a Case, Lang);
+
+impl fmt::Display for CaseEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let CaseEntry(number, case, lang) = self;
+
+        write!(f, "**#{}** `{}` {} by {} <t:{}>", number, case.action.name(), UserId(case.target).mention(), UserId(case.moderator).mention(), case.time)?;
+        if case.duration > 0 {
+            write!(f, " for {}", crate::utils::format_duration(time::Duration::from_secs(case.duration)))?;
+        }
+
+        match case.reason.as_str() {
+            "" => write!(f, ": {}", Msg::NoReason.get(*lang)),
+            reason => write!(f, ": {}", reason),
+        }
+    }
+}
+
+///Stores case under the next free number, returning it.
+pub fn open_case(db: &DbView, server: u64, case: &Case) -> Option<u64> {
+    loop {
+        let number = db.last::<Case, _>(server).map(|((_, number), _)| number + 1).unwrap_or(1);
+        match db.put_new((server, number), case) {
+            Ok(true) => break Some(number),
+            //Someone else took this number first
+            Ok(false) => continue,
+            Err(_) => break None,
+        }
+    }
+}
+
+///Parses optional duration of ban or mute.
+///
+///Unit is mandatory, so that reason may start with number, e.g. `3 strikes`.
+fn take_duration(args: &mut Args<'_>) -> Option<time::Duration> {
+    let mut next = args.clone();
+    let duration = next.word().filter(|word| word.ends_with(|ch: char| ch.is_ascii_alphabetic())).and_then(crate::utils::parse_duration)?;
+    *args = next;
+    Some(duration)
+}
+
+impl Handler {
+    //Parses `<user> [duration] [reason]` and performs action
+    async fn moderate(&self, ctx: HandlerContext<'_>, action: CaseAction) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let target = try_arg!(ctx, args.next::<UserId>("user"));
+        let duration = match action {
+            CaseAction::Ban | CaseAction::Mute => take_duration(&mut args).unwrap_or_default(),
+            _ => time::Duration::from_secs(0),
+        };
+        let reason = args.rest();
+
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        if target == ctx.author.id || target.0 == self.state.info.id {
+            return ctx.reply(ctx.tr(Msg::CannotModerate)).await;
+        }
+        if action != CaseAction::Unban {
+            if let Ok(member) = server.member(ctx, target).await {
+                if self.is_moderator(server.0, &member).await {
+                    return ctx.reply(ctx.tr(Msg::CannotModerate)).await;
+                }
+            }
+        }
+
+        let server_info = match self.state.db.get::<data::Server>(server.0) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let http = &ctx.serenity.http;
+        let result = match action {
+            CaseAction::Warn => {
+                let server_name = match ctx.guild().await {
+                    Some(guild) => guild.name,
+                    None => server.0.to_string(),
+                };
+                let reason = match reason {
+                    "" => ctx.tr(Msg::NoReason),
+                    reason => reason,
+                };
+                let text = ctx.tr_with(Msg::Warned, &[("server", &server_name), ("reason", &reason)]);
+                //User may have DMs closed, warning is recorded regardless
+                if let Ok(channel) = target.create_dm_channel(ctx).await {
+                    let _ = channel.say(http, text).await;
+                }
+                Ok(())
+            },
+            CaseAction::Kick if reason.is_empty() => server.kick(http, target).await,
+            CaseAction::Kick => server.kick_with_reason(http, target, reason).await,
+            CaseAction::Ban if reason.is_empty() => server.ban(http, target, 0).await,
+            CaseAction::Ban => server.ban_with_reason(http, target, 0, reason).await,
+            CaseAction::Unban => server.unban(http, target).await,
+            CaseAction::Mute | CaseAction::Unmute if server_info.mute_role == 0 => {
+                return ctx.reply(ctx.tr(Msg::NoMuteRole)).await;
+            },
+            CaseAction::Mute => http.add_member_role(server.0, target.0, server_info.mute_role).await,
+            CaseAction::Unmute => http.remove_member_role(server.0, target.0, server_info.mute_role).await,
+        };
+
+        if let Err(error) = result {
+            rogu::warn!("Unable to {} user {}: {}", action.name(), target.0, error);
+            return ctx.reply(ctx.tr_with(Msg::ActionFailed, &[("action", &action.name())])).await;
+        }
+
+        let expiry = match action {
+            CaseAction::Ban | CaseAction::Unban => Some(CaseAction::Ban),
+            CaseAction::Mute | CaseAction::Unmute => Some(CaseAction::Mute),
+            _ => None,
+        };
+        if let Some(expiry_action) = expiry {
+            let db = self.state.db.clone();
+            let (server_id, target_id, role) = (server.0, target.0, server_info.mute_role);
+            //Manual action overrides pending expiration, so that it is not lifted unexpectedly
+            //Duration is only parsed for ban and mute
+            let _ = tokio::task::spawn_blocking(move || match duration.as_secs() {
+                0 => scheduler::cancel(&db, server_id, target_id, expiry_action),
+                _ => scheduler::schedule(&db, server_id, target_id, Expiry { action: expiry_action, role }, duration),
+            }).await;
+
+            let data = ctx.serenity.data.read().await;
+            if let Some(sender) = data.get::<SchedulerSendTag>() {
+                let _ = sender.send(scheduler::SchedulerCommand::Refresh).await;
+            }
+        }
+
+        let case = Case {
+            action,
+            target: target.0,
+            moderator: ctx.author.id.0,
+            time: time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0),
+            duration: duration.as_secs(),
+            reason: reason.to_owned(),
+        };
+
+        let db = self.state.db.clone();
+        let server_id = server.0;
+        match tokio::task::spawn_blocking(move || open_case(&db, server_id, &case)).await {
+            Ok(Some(number)) => ctx.reply(ctx.tr_with(Msg::CaseOpened, &[("case", &number), ("action", &action.name()), ("user", &target.mention())])).await,
+            _ => ctx.react(emoji::KINSHI).await,
+        }
+    }
+
+    #[inline]
+    pub async fn handle_warn(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Warn).await
+    }
+
+    #[inline]
+    pub async fn handle_kick(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Kick).await
+    }
+
+    #[inline]
+    pub async fn handle_ban(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Ban).await
+    }
+
+    #[inline]
+    pub async fn handle_unban(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Unban).await
+    }
+
+    #[inline]
+    pub async fn handle_mute(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Mute).await
+    }
+
+    #[inline]
+    pub async fn handle_unmute(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        self.moderate(ctx, CaseAction::Unmute).await
+    }
+
+    #[inline]
+    pub async fn handle_mute_role(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let role = try_arg!(ctx, args.text("role"));
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        let role = match role {
+            "reset" => 0,
+            role => match super::utils::find_role(role, &guild.roles) {
+                Some(role) => role.0,
+                None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+            },
+        };
+
+        let id = guild.id.0;
+        let mut server = match self.state.db.get::<data::Server>(id) {
+            Ok(server) => server,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+        server.mute_role = role;
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+
+        ctx.react(emoji::OK).await
+    }
+
+    #[inline]
+    pub async fn handle_cases(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let target = try_arg!(ctx, Args::new(ctx.args).next::<UserId>("user"));
+
+        let server = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let cases: Vec<_> = self.state.db.scan::<Case, _>(server).filter(|(_, case)| case.target == target.0).collect();
+        if cases.is_empty() {
+            return ctx.reply(ctx.tr_with(Msg::NoCases, &[("user", &target.mention())])).await;
+        }
+
+        let mut text = String::new();
+        //Show the most recent ones
+        for ((_, number), case) in cases.iter().skip(cases.len().saturating_sub(MAX_CASES)) {
+            text.push_str(&CaseEntry(*number, case, ctx.lang).to_string());
+            text.push('\n');
+        }
+
+        ctx.reply_embed_private(|m| m.title(format_args!("Cases ({})", cases.len())).description(text)).await?;
+        ctx.react(emoji::OK).await
+    }
+
+    #[inline]
+    pub async fn handle_case(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let number = try_arg!(ctx, args.next::<u64>("case"));
+        let amend = match args.word() {
+            Some("reason") => Some(try_arg!(ctx, args.text("reason"))),
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("option")).await,
+            None => None,
+        };
+
+        let server = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut case = match self.state.db.get::<Case>((server, number)) {
+            //Unused number is default case without target
+            Ok(case) if case.target != 0 => case,
+            Ok(_) => return ctx.reply(ctx.tr_with(Msg::CaseNotFound, &[("case", &number)])).await,
+            Err(error) => {
+                rogu::error!("Unable to get case: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        match amend {
+            Some(reason) => {
+                case.reason = reason.to_owned();
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put((server, number), &case)).await;
+                ctx.react(emoji::OK).await
+            },
+            None => ctx.reply(CaseEntry(number, &case, ctx.lang)).await,
+        }
+    }
+
+    #[inline]
+    pub async fn handle_purge(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let count = try_arg!(ctx, args.next::<u16>("count")) as usize;
+        if count == 0 || count > MAX_PURGE {
+            return ctx.reply_usage(ArgError::Invalid("count")).await;
+        }
+        let filter = match PurgeFilter::parse(args.rest()) {
+            Some(filter) => filter,
+            None => return ctx.reply_usage(ArgError::Invalid("filter")).await,
+        };
+
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let http = &ctx.serenity.http;
+        //Command itself is not purged
+        let mut before = match ctx.source {
+            Source::Message(msg) => Some(msg.id),
+            Source::Interaction(..) => None,
+        };
+        let mut scanned = 0;
+        let mut selected = Vec::new();
+        while selected.len() < count && scanned < MAX_PURGE_SCAN {
+            let batch = ctx.channel_id.messages(http, |retriever| match before {
+                Some(before) => retriever.before(before).limit(MAX_BULK_DELETE as u64),
+                None => retriever.limit(MAX_BULK_DELETE as u64),
+            }).await?;
+
+            let last = match batch.last() {
+                Some(last) => last.id,
+                None => break,
+            };
+            scanned += batch.len();
+            before = Some(last);
+
+            //Messages are ordered from the newest
+            selected.extend(batch.iter().filter(|msg| !msg.pinned && filter.matches(msg)).map(|msg| msg.id).take(count - selected.len()));
+        }
+
+        let now = time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+        let (recent, old): (Vec<MessageId>, Vec<MessageId>) = selected.into_iter().partition(|id| now.saturating_sub(super::utils::snowflake_time(id.0)) < MAX_BULK_DELETE_AGE);
+
+        //Purge is logged as a whole
+        let mut deleted = 0;
+        for chunk in recent.chunks(MAX_BULK_DELETE) {
+            self.own_deletions.mark(chunk);
+            let result = match chunk {
+                [id] => ctx.channel_id.delete_message(http, *id).await,
+                chunk => ctx.channel_id.delete_messages(http, chunk).await,
+            };
+            match result {
+                Ok(()) => deleted += chunk.len(),
+                Err(error) => {
+                    self.own_deletions.unmark(chunk);
+                    rogu::warn!("Unable to bulk delete messages: {}", error);
+                },
+            }
+        }
+        //Old messages can be deleted only one by one
+        for id in old {
+            self.own_deletions.mark(&[id]);
+            match ctx.channel_id.delete_message(http, id).await {
+                Ok(()) => deleted += 1,
+                Err(error) => {
+                    self.own_deletions.unmark(&[id]);
+                    rogu::warn!("Unable to delete message: {}", error);
+                },
+            }
+        }
+
+        self.log_purge(ctx.serenity, server, ctx.author, ctx.channel_id, deleted, &filter).await;
+        ctx.reply(ctx.tr_with(Msg::Purged, &[("count", &deleted)])).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_purge_filter() {
+        assert_eq!(PurgeFilter::parse(""), Some(PurgeFilter::All));
+        assert_eq!(PurgeFilter::parse("bots"), Some(PurgeFilter::Bots));
+        assert_eq!(PurgeFilter::parse("attachments"), Some(PurgeFilter::Attachments));
+        assert_eq!(PurgeFilter::parse("<@!5>"), Some(PurgeFilter::User(UserId(5))));
+        assert_eq!(PurgeFilter::parse("contains: Free Nitro"), Some(PurgeFilter::Contains("free nitro".to_owned())));
+        assert_eq!(PurgeFilter::parse("contains:"), None);
+        assert_eq!(PurgeFilter::parse("everything"), None);
+    }
+
+    #[test]
+    fn should_require_unit_of_duration() {
+        let mut args = Args::new("3 strikes");
+        assert_eq!(take_duration(&mut args), None);
+        assert_eq!(args.rest(), "3 strikes");
+
+        let mut args = Args::new("1h30m spam");
+        assert_eq!(take_duration(&mut args), Some(time::Duration::from_secs(90 * 60)));
+        assert_eq!(args.rest(), "spam");
+
+        let mut args = Args::new("2days of spam");
+        assert_eq!(take_duration(&mut args), None);
+        assert_eq!(args.rest(), "2days of spam");
+    }
+}
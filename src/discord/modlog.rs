@@ -0,0 +1,299 @@
+//! Audit log of server's events
+
+use super::*;
+use super::registry::Command;
+
+use core::fmt::Write;
+
+use serenity::builder::CreateEmbed;
+
+use std::time;
+
+//Limit on embed's field value
+const MAX_FIELD: usize = 1024;
+//Number of tracked deletions, after which stale ones are removed
+const CLEANUP_THRESHOLD: usize = 1024;
+//Deletion event is expected to arrive in this time
+const DELETION_TIMEOUT: time::Duration = time::Duration::from_secs(60);
+
+const COLOR_MESSAGE: u32 = 0x3498DB;
+const COLOR_JOIN: u32 = 0x2ECC71;
+const COLOR_LEAVE: u32 = 0xE67E22;
+const COLOR_MEMBER: u32 = 0x9B59B6;
+const COLOR_BAN: u32 = 0xE74C3C;
+const COLOR_COMMAND: u32 = 0x95A5A6;
+
+//Message's content, fitted into embed's field
+struct Content<'a>(&'a str);
+
+impl fmt::Display for Content<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const ELLIPSIS: char = '…';
+
+        match self.0 {
+            "" => f.write_str("*No text*"),
+            text if text.len() > MAX_FIELD => write!(f, "{}{}", crate::utils::truncate(text, MAX_FIELD - ELLIPSIS.len_utf8()), ELLIPSIS),
+            text => f.write_str(text),
+        }
+    }
+}
+
+struct Roles<'a>(&'a [RoleId]);
+
+impl fmt::Display for Roles<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut roles = self.0.iter();
+        if let Some(role) = roles.next() {
+            write!(f, "{}", role.mention())?;
+        }
+
+        for role in roles {
+            write!(f, ", {}", role.mention())?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Link(GuildId, ChannelId, MessageId);
+
+impl fmt::Display for Link {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "https://discord.com/channels/{}/{}/{}", (self.0).0, (self.1).0, (self.2).0)
+    }
+}
+
+#[derive(Default)]
+///Messages, that bot deletes itself, as they are logged by their cause.
+pub(super) struct OwnDeletions {
+    ids: std::sync::Mutex<HashMap<u64, time::Instant>>,
+}
+
+impl OwnDeletions {
+    #[inline]
+    fn ids(&self) -> std::sync::MutexGuard<'_, HashMap<u64, time::Instant>> {
+        match self.ids.lock() {
+            Ok(ids) => ids,
+            Err(error) => error.into_inner(),
+        }
+    }
+
+    ///Marks messages as deleted by bot, before deleting them.
+    pub(super) fn mark(&self, messages: &[MessageId]) {
+        let now = time::Instant::now();
+        let mut ids = self.ids();
+        if ids.len() >= CLEANUP_THRESHOLD {
+            //Deletion might fail or its event might be lost
+            ids.retain(|_, time| now.saturating_duration_since(*time) < DELETION_TIMEOUT);
+        }
+        ids.extend(messages.iter().map(|id| (id.0, now)));
+    }
+
+    ///Unmarks messages, which bot failed to delete.
+    pub(super) fn unmark(&self, messages: &[MessageId]) {
+        let mut ids = self.ids();
+        for id in messages {
+            ids.remove(&id.0);
+        }
+    }
+
+    ///Returns whether message was deleted by bot, forgetting it.
+    fn take(&self, message: MessageId) -> bool {
+        self.ids().remove(&message.0).is_some()
+    }
+}
+
+#[inline]
+fn user_author<'a>(embed: &'a mut CreateEmbed, user: &User) -> &'a mut CreateEmbed {
+    embed.author(|author| author.name(user.tag()).icon_url(user.face()))
+         .footer(|footer| footer.text(format_args!("User ID: {}", user.id.0)))
+}
+
+impl Handler {
+    ///Posts embed into server's mod-log channel, if it is set.
+    pub(super) async fn modlog<F: FnOnce(&mut CreateEmbed) -> &mut CreateEmbed>(&self, ctx: &Context, server: GuildId, embed: F) {
+        let modlog_ch = match self.state.db.get::<data::Server>(server.0) {
+            Ok(server_info) if server_info.modlog_ch != 0 => ChannelId(server_info.modlog_ch),
+            Ok(_) => return,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return;
+            }
+        };
+
+        let mut result = CreateEmbed::default();
+        embed(&mut result);
+
+        if let Err(error) = modlog_ch.send_message(&ctx.http, |msg| msg.set_embed(result)).await {
+            rogu::warn!("Unable to post into mod-log of server {}: {}", server.0, error);
+        }
+    }
+
+    pub(super) async fn log_message_update(&self, ctx: &Context, old: Option<Message>, event: &MessageUpdateEvent) {
+        let (server, author, content) = match (event.guild_id, event.author.as_ref(), event.content.as_ref()) {
+            //Bots are noisy, and updates without content are just embeds being loaded
+            (Some(server), Some(author), Some(content)) if !author.bot => (server, author, content),
+            _ => return,
+        };
+
+        let old = match old {
+            Some(old) if old.content == *content => return,
+            Some(old) => Content(&old.content).to_string(),
+            None => "*Not cached*".to_owned(),
+        };
+
+        self.modlog(ctx, server, |embed| {
+            user_author(embed, author).colour(COLOR_MESSAGE)
+                                      .title("Message edited")
+                                      .description(format_args!("In {} [Jump]({})", event.channel_id.mention(), Link(server, event.channel_id, event.id)))
+                                      .field("Before", old, false)
+                                      .field("After", Content(content), false)
+        }).await
+    }
+
+    pub(super) async fn log_message_delete(&self, ctx: &Context, server: GuildId, channel: ChannelId, id: MessageId) {
+        if self.own_deletions.take(id) {
+            return;
+        }
+
+        let msg = ctx.cache.message(channel, id).await;
+        if msg.as_ref().map(|msg| msg.author.bot).unwrap_or(false) {
+            return;
+        }
+
+        self.modlog(ctx, server, |embed| {
+            embed.colour(COLOR_MESSAGE).title("Message deleted");
+            match msg.as_ref() {
+                Some(msg) => user_author(embed, &msg.author).description(format_args!("In {}", channel.mention()))
+                                                            .field("Content", Content(&msg.content), false),
+                None => embed.description(format_args!("In {}, content is not cached", channel.mention())),
+            }
+        }).await
+    }
+
+    pub(super) async fn log_message_delete_bulk(&self, ctx: &Context, server: GuildId, channel: ChannelId, ids: &[MessageId]) {
+        let ids: Vec<_> = ids.iter().copied().filter(|id| !self.own_deletions.take(*id)).collect();
+        if ids.is_empty() {
+            return;
+        }
+
+        let mut messages = String::new();
+        for id in ids.iter() {
+            if let Some(msg) = ctx.cache.message(channel, *id).await {
+                if !msg.author.bot {
+                    let _ = writeln!(messages, "**{}**: {}", msg.author.tag(), msg.content);
+                }
+            }
+        }
+
+        self.modlog(ctx, server, |embed| {
+            embed.colour(COLOR_MESSAGE)
+                 .title("Messages deleted")
+                 .description(format_args!("{} messages in {}", ids.len(), channel.mention()));
+            match messages.as_str() {
+                "" => embed,
+                messages => embed.field("Cached content", Content(messages), false),
+            }
+        }).await
+    }
+
+    pub(super) async fn log_member_join(&self, ctx: &Context, member: &Member) {
+        let user = &member.user;
+        self.modlog(ctx, member.guild_id, |embed| {
+            user_author(embed, user).colour(COLOR_JOIN)
+                                    .title("Member joined")
+                                    .description(format_args!("{} {}", user.mention(), user.tag()))
+                                    .field("Account created", format_args!("<t:{}:R>", user.id.created_at().timestamp()), false)
+        }).await
+    }
+
+    pub(super) async fn log_member_leave(&self, ctx: &Context, server: GuildId, user: &User, member: Option<&Member>) {
+        self.modlog(ctx, server, |embed| {
+            user_author(embed, user).colour(COLOR_LEAVE)
+                                    .title("Member left")
+                                    .description(format_args!("{} {}", user.mention(), user.tag()));
+            match member {
+                Some(member) if !member.roles.is_empty() => embed.field("Roles", Roles(&member.roles), false),
+                _ => embed,
+            }
+        }).await
+    }
+
+    pub(super) async fn log_member_update(&self, ctx: &Context, old: Option<&Member>, new: &Member) {
+        //Without previous state there is nothing to compare with
+        let old = match old {
+            Some(old) => old,
+            None => return,
+        };
+
+        let added: Vec<_> = new.roles.iter().copied().filter(|role| !old.roles.contains(role)).collect();
+        let removed: Vec<_> = old.roles.iter().copied().filter(|role| !new.roles.contains(role)).collect();
+        let is_nick_changed = old.nick != new.nick;
+
+        if added.is_empty() && removed.is_empty() && !is_nick_changed {
+            return;
+        }
+
+        self.modlog(ctx, new.guild_id, |embed| {
+            user_author(embed, &new.user).colour(COLOR_MEMBER)
+                                         .title("Member updated")
+                                         .description(new.user.mention());
+            if !added.is_empty() {
+                embed.field("Roles added", Roles(&added), false);
+            }
+            if !removed.is_empty() {
+                embed.field("Roles removed", Roles(&removed), false);
+            }
+            if is_nick_changed {
+                embed.field("Nickname", format_args!("{} → {}", old.nick.as_deref().unwrap_or("*None*"), new.nick.as_deref().unwrap_or("*None*")), false);
+            }
+            embed
+        }).await
+    }
+
+    pub(super) async fn log_auto_mod(&self, ctx: &Context, server: GuildId, msg: &Message, filter: data::Filter, action: data::FilterAction) {
+        self.modlog(ctx, server, |embed| {
+            user_author(embed, &msg.author).colour(COLOR_BAN)
+                                           .title(format_args!("Auto-mod: {}", filter.name()))
+                                           .description(format_args!("{} in {}, action: {}", msg.author.mention(), msg.channel_id.mention(), action.name()))
+                                           .field("Content", Content(&msg.content), false)
+        }).await
+    }
+
+    pub(super) async fn log_purge<T: fmt::Display>(&self, ctx: &Context, server: GuildId, moderator: &User, channel: ChannelId, count: usize, filter: T) {
+        self.modlog(ctx, server, |embed| {
+            user_author(embed, moderator).colour(COLOR_MESSAGE)
+                                         .title("Messages purged")
+                                         .description(format_args!("{} removed {} messages in {}", moderator.mention(), count, channel.mention()))
+                                         .field("Filter", filter, false)
+        }).await
+    }
+
+    pub(super) async fn log_ban(&self, ctx: &Context, server: GuildId, user: &User, is_banned: bool) {
+        self.modlog(ctx, server, |embed| {
+            user_author(embed, user).colour(COLOR_BAN)
+                                    .title(if is_banned { "Member banned" } else { "Member unbanned" })
+                                    .description(format_args!("{} {}", user.mention(), user.tag()))
+        }).await
+    }
+
+    ///Records usage of moderator's command, before it is executed.
+    pub(super) async fn log_command(&self, ctx: &HandlerContext<'_>, command: &Command) {
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => return,
+        };
+
+        let text = match ctx.args {
+            "" => command.name.to_owned(),
+            args => format!("{} {}", command.name, crate::utils::truncate(args, MAX_FIELD)),
+        };
+
+        self.modlog(ctx.serenity, server, |embed| {
+            user_author(embed, ctx.author).colour(COLOR_COMMAND)
+                                          .title("Command used")
+                                          .description(format_args!("{} used `{}` in {}", ctx.author.mention(), text, ctx.channel_id.mention()))
+        }).await
+    }
+}
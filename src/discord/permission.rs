@@ -0,0 +1,113 @@
+use super::*;
+use crate::data::PermissionTarget;
+use super::registry::{Access, Command};
+
+impl Handler {
+    ///Determines whether command can be executed within context.
+    ///
+    ///Rules are applied in order of precedence: user, channel's deny, role, channel's allow and finally `@everyone` role.
+    ///Allow rules of moderation commands only apply to users and roles.
+    ///When no rule matches, command's default access is used.
+    pub fn is_permitted(&self, ctx: &HandlerContext<'_>, command: &Command) -> bool {
+        let default = match command.access {
+            Access::Everyone => true,
+            Access::Mod | Access::Locked => ctx.is_mod,
+        };
+
+        let server = match ctx.guild_id {
+            Some(server) => server.0,
+            None => return default,
+        };
+
+        if command.access == Access::Locked {
+            return default;
+        }
+
+        let mut user = None;
+        let mut role = None;
+        let mut channel = None;
+        let mut everyone = None;
+
+        for ((_, _, target), rule) in self.state.db.scan::<data::Permission, _>((server, command.id())) {
+            match rule.target {
+                PermissionTarget::User if target == ctx.author.id.0 => user = Some(rule.allow),
+                //@everyone role has the same id as server
+                PermissionTarget::Role if target == server => everyone = Some(rule.allow),
+                //Deny takes priority among roles
+                PermissionTarget::Role if ctx.roles.iter().any(|id| id.0 == target) => role = Some(role.unwrap_or(true) && rule.allow),
+                PermissionTarget::Channel if target == ctx.channel_id.0 => channel = Some(rule.allow),
+                _ => (),
+            }
+        }
+
+        //Moderation commands can be allowed only to chosen roles or users, not to everyone in channel
+        if command.access == Access::Mod {
+            channel = channel.filter(|allow| !allow);
+            everyone = everyone.filter(|allow| !allow);
+        }
+
+        //Channel's deny restricts every role, only user's own rule overrides it
+        if channel == Some(false) {
+            return user.unwrap_or(false);
+        }
+
+        user.or(role).or(channel).or(everyone).unwrap_or(default)
+    }
+
+    ///Returns help, listing only commands, that can be executed within context.
+    pub fn help_text(&self, ctx: &HandlerContext<'_>) -> String {
+        use core::fmt::Write;
+
+        let mut general = String::new();
+        let mut moderation = String::new();
+
+        for command in registry::COMMANDS.iter().filter(|command| self.is_permitted(ctx, command)) {
+            let section = match command.access {
+                Access::Everyone => &mut general,
+                Access::Mod | Access::Locked => &mut moderation,
+            };
+
+            let _ = write!(section, "- `{}` - {}", command.usage, command.description);
+            if !command.aliases.is_empty() {
+                let _ = write!(section, " Aliases: `{}`", command.aliases.join("`, `"));
+            }
+            section.push('\n');
+        }
+
+        let mut result = String::new();
+        if !general.is_empty() {
+            result.push_str("__General Commands__\n");
+            result.push_str(&general);
+        }
+        if !moderation.is_empty() {
+            result.push_str("__Mod Commands__\n");
+            result.push_str(&moderation);
+        }
+
+        result
+    }
+}
+
+///Parses rule's target from mention, id or name.
+pub fn find_target(text: &str, guild: &Guild) -> Option<(PermissionTarget, u64)> {
+    if text == "everyone" || text == "@everyone" {
+        return Some((PermissionTarget::Role, guild.id.0));
+    }
+
+    if let Some(id) = text.strip_prefix("<#").and_then(|text| text.strip_suffix('>')) {
+        return id.parse().ok().map(|id| (PermissionTarget::Channel, id));
+    }
+
+    if let Some(id) = text.strip_prefix("<@").and_then(|text| text.strip_suffix('>')) {
+        if !id.starts_with('&') {
+            let id = id.strip_prefix('!').unwrap_or(id);
+            return id.parse().ok().map(|id| (PermissionTarget::User, id));
+        }
+    }
+
+    if let Some(role) = utils::find_role(text, &guild.roles) {
+        return Some((PermissionTarget::Role, role.0));
+    }
+
+    guild.channels.values().find(|channel| channel.name == text.trim_start_matches('#')).map(|channel| (PermissionTarget::Channel, channel.id.0))
+}
@@ -0,0 +1,284 @@
+//! Raid protection
+
+use super::*;
+use super::args::{Args, ArgError};
+use crate::data::{Raid, RaidAction};
+
+use std::collections::VecDeque;
+use std::time;
+
+const DAY: u64 = 24 * 60 * 60;
+//Period of join rate, unless specified
+const DEFAULT_PERIOD: u16 = 10;
+//Number of tracked servers, after which stale ones are removed
+const CLEANUP_THRESHOLD: usize = 1024;
+const REASON: &str = "Raid protection";
+
+#[derive(Debug, PartialEq)]
+enum Trigger {
+    //Number of joins within period
+    Rate(usize),
+    //Account's age in days
+    Age(u64),
+}
+
+//Determines whether join should turn on raid mode.
+fn check_trigger(settings: &Raid, joins: usize, age: u64) -> Option<Trigger> {
+    if settings.joins > 0 && joins >= settings.joins as usize {
+        Some(Trigger::Rate(joins))
+    } else if settings.min_age > 0 && age < settings.min_age as u64 * DAY {
+        Some(Trigger::Age(age / DAY))
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+///Tracks recent joins per server.
+pub(super) struct JoinTracker {
+    joins: std::sync::Mutex<HashMap<u64, VecDeque<time::Instant>>>,
+}
+
+impl JoinTracker {
+    ///Records join, returning number of joins within period.
+    fn record(&self, server: u64, period: time::Duration, now: time::Instant) -> usize {
+        let mut joins = match self.joins.lock() {
+            Ok(joins) => joins,
+            Err(error) => error.into_inner(),
+        };
+
+        if joins.len() >= CLEANUP_THRESHOLD {
+            //Period cannot exceed u16 seconds
+            joins.retain(|_, recent| recent.back().map(|last| now.saturating_duration_since(*last).as_secs() <= u16::max_value() as u64).unwrap_or(false));
+        }
+
+        let recent = joins.entry(server).or_default();
+        while let Some(first) = recent.front() {
+            if now.saturating_duration_since(*first) < period {
+                break;
+            }
+            recent.pop_front();
+        }
+        recent.push_back(now);
+
+        recent.len()
+    }
+
+    fn reset(&self, server: u64) {
+        let mut joins = match self.joins.lock() {
+            Ok(joins) => joins,
+            Err(error) => error.into_inner(),
+        };
+        joins.remove(&server);
+    }
+}
+
+impl Handler {
+    async fn notify_raid(&self, ctx: &Context, server: u64, settings: &Raid, trigger: Trigger) {
+        let server_info = match self.state.db.get::<data::Server>(server) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return;
+            }
+        };
+
+        let lang = server_info.lang;
+        let reason = match trigger {
+            Trigger::Rate(joins) => crate::utils::render_template(Msg::RaidJoinRate.get(lang), &[("joins", &joins), ("secs", &settings.period)]),
+            Trigger::Age(days) => crate::utils::render_template(Msg::RaidAccountAge.get(lang), &[("days", &days)]),
+        };
+        let owner = self.mods.read().await.get(&server).map(|mods| UserId(mods.owner));
+
+        let mut mods = String::new();
+        for role in server_info.mod_roles.iter().filter(|role| **role != 0) {
+            mods.push_str(&RoleId(*role).mention().to_string());
+            mods.push(' ');
+        }
+        //Alert must ping someone, even if moderator roles aren't configured
+        if let (true, Some(owner)) = (mods.is_empty(), owner) {
+            mods.push_str(&owner.mention().to_string());
+            mods.push(' ');
+        }
+        let text = crate::utils::render_template(Msg::RaidDetected.get(lang), &[("mods", &mods), ("reason", &reason), ("action", &settings.action.name())]);
+        let result = match server_info.modlog_ch {
+            //Without mod-log only owner is certain to be moderator
+            0 => match owner {
+                Some(owner) => match owner.create_dm_channel(ctx).await {
+                    Ok(channel) => channel.say(ctx, text).await.map(|_| ()),
+                    Err(error) => Err(error),
+                },
+                None => Ok(()),
+            },
+            modlog_ch => ChannelId(modlog_ch).say(ctx, text).await.map(|_| ()),
+        };
+
+        if let Err(error) = result {
+            rogu::warn!("Unable to notify about raid on server {}: {}", server, error);
+        }
+    }
+
+    ///Tracks member's join, returning action applied to member, if server is under raid.
+    pub(super) async fn check_raid(&self, ctx: &Context, member: &Member) -> Option<RaidAction> {
+        let server = member.guild_id;
+        let mut settings = match self.state.db.get::<Raid>(server.0) {
+            Ok(settings) => settings,
+            Err(error) => {
+                rogu::error!("Unable to get raid settings: {}", error);
+                return None;
+            }
+        };
+
+        if !settings.is_active {
+            if settings.joins == 0 && settings.min_age == 0 {
+                return None;
+            }
+
+            let joins = match settings.joins {
+                0 => 0,
+                _ => self.joins.record(server.0, time::Duration::from_secs(settings.period as u64), time::Instant::now()),
+            };
+            let now = time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0);
+            let age = now.saturating_sub(super::utils::snowflake_time(member.user.id.0));
+
+            let trigger = check_trigger(&settings, joins, age)?;
+            rogu::info!("Server {} enters raid mode: {:?}", server.0, trigger);
+
+            settings.is_active = true;
+            let db = self.state.db.clone();
+            let (id, raid) = (server.0, settings.clone());
+            let _ = tokio::task::spawn_blocking(move || db.put(id, &raid)).await;
+
+            self.notify_raid(ctx, server.0, &settings, trigger).await;
+        }
+
+        let result = match settings.action {
+            RaidAction::Nothing => Ok(()),
+            RaidAction::Kick => server.kick_with_reason(&ctx.http, member.user.id, REASON).await,
+            RaidAction::Quarantine if settings.quarantine_role == 0 => {
+                rogu::warn!("Server {} has no quarantine role", server.0);
+                Ok(())
+            },
+            RaidAction::Quarantine => ctx.http.add_member_role(server.0, member.user.id.0, settings.quarantine_role).await,
+        };
+
+        if let Err(error) = result {
+            rogu::warn!("Unable to {} user {} during raid: {}", settings.action.name(), member.user.id.0, error);
+        }
+
+        Some(settings.action)
+    }
+
+    #[inline]
+    pub async fn handle_raid(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let id = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut settings = match self.state.db.get::<Raid>(id) {
+            Ok(settings) => settings,
+            Err(error) => {
+                rogu::error!("Unable to get raid settings: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        match args.word() {
+            None => {
+                let rate = match settings.joins {
+                    0 => "Off".to_owned(),
+                    joins => format!("{} joins within {}s", joins, settings.period),
+                };
+                let age = match settings.min_age {
+                    0 => "Off".to_owned(),
+                    days => format!("{}d", days),
+                };
+
+                ctx.reply_embed_private(|m| {
+                    m.title("Raid protection")
+                     .field("Raid mode", if settings.is_active { "On" } else { "Off" }, false)
+                     .field("Join rate", rate, false)
+                     .field("Minimum account age", age, false)
+                     .field("Action", settings.action.name(), false)
+                     .field("Quarantine role", RoleId(settings.quarantine_role).mention(), false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("start") => settings.is_active = true,
+            Some("lift") => {
+                settings.is_active = false;
+                self.joins.reset(id);
+            },
+            Some("rate") => {
+                settings.joins = try_arg!(ctx, args.next::<u16>("joins"));
+                settings.period = match try_arg!(ctx, args.optional::<u16>("secs")) {
+                    Some(0) => return ctx.reply_usage(ArgError::Invalid("secs")).await,
+                    Some(period) => period,
+                    None => DEFAULT_PERIOD,
+                };
+            },
+            Some("age") => settings.min_age = try_arg!(ctx, args.next::<u16>("days")),
+            Some("action") => match RaidAction::from_name(try_arg!(ctx, args.next::<&str>("action"))) {
+                Some(action) => settings.action = action,
+                None => return ctx.reply_usage(ArgError::Invalid("action")).await,
+            },
+            Some("role") => {
+                let role = try_arg!(ctx, args.text("role"));
+                settings.quarantine_role = match role {
+                    "reset" => 0,
+                    role => match ctx.guild().await.and_then(|guild| super::utils::find_role(role, &guild.roles)) {
+                        Some(role) => role.0,
+                        None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+                    },
+                };
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("setting")).await,
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &settings)).await;
+
+        ctx.react(emoji::OK).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_count_joins_within_period() {
+        let tracker = JoinTracker::default();
+        let period = time::Duration::from_secs(10);
+        let now = time::Instant::now();
+
+        assert_eq!(tracker.record(1, period, now), 1);
+        assert_eq!(tracker.record(1, period, now + time::Duration::from_secs(5)), 2);
+        assert_eq!(tracker.record(2, period, now + time::Duration::from_secs(5)), 1);
+        assert_eq!(tracker.record(1, period, now + time::Duration::from_secs(12)), 2);
+
+        tracker.reset(1);
+        assert_eq!(tracker.record(1, period, now + time::Duration::from_secs(13)), 1);
+    }
+
+    #[test]
+    fn should_trigger_raid_mode() {
+        let mut settings = Raid::default();
+        assert_eq!(check_trigger(&settings, 100, 0), None);
+
+        settings.joins = 5;
+        assert_eq!(check_trigger(&settings, 4, 0), None);
+        assert_eq!(check_trigger(&settings, 5, 0), Some(Trigger::Rate(5)));
+
+        settings.min_age = 7;
+        assert_eq!(check_trigger(&settings, 1, 2 * DAY + 5), Some(Trigger::Age(2)));
+        assert_eq!(check_trigger(&settings, 1, 7 * DAY), None);
+    }
+}
@@ -0,0 +1,284 @@
+//! Commands rate limiting
+
+use super::*;
+use super::args::{Args, ArgError};
+use super::registry::{self, Command};
+
+use core::fmt::Write;
+use std::time;
+
+//Number of tracked entries, after which expired ones are removed
+const CLEANUP_THRESHOLD: usize = 1024;
+//Older entries are removed on cleanup, so longer periods would not hold
+const MAX_PERIOD: time::Duration = time::Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Limit on number of command's uses within period.
+pub(super) struct Bucket {
+    pub(super) uses: u16,
+    pub(super) period: time::Duration,
+    ///Whether moderators are not limited.
+    pub(super) mod_exempt: bool,
+}
+
+impl Bucket {
+    #[inline]
+    ///Creates bucket, allowing `uses` per `secs`, moderators are exempted.
+    pub(super) const fn new(uses: u16, secs: u64) -> Self {
+        Self {
+            uses,
+            period: time::Duration::from_secs(secs),
+            mod_exempt: true,
+        }
+    }
+
+    #[inline]
+    ///Makes bucket to apply to moderators too.
+    pub(super) const fn strict(mut self) -> Self {
+        self.mod_exempt = false;
+        self
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) enum Check {
+    Allowed,
+    ///Limit is reached, `notify` is set only for the first invocation over the limit.
+    Limited {
+        retry: time::Duration,
+        notify: bool,
+    },
+}
+
+struct Window {
+    start: time::Instant,
+    used: u16,
+    notified: bool,
+}
+
+#[derive(Default)]
+///Tracks usage per (server, user, command)
+pub(super) struct RateLimiter {
+    windows: std::sync::Mutex<HashMap<(u64, u64, u64), Window>>,
+}
+
+impl RateLimiter {
+    pub(super) fn check(&self, key: (u64, u64, u64), bucket: Bucket, now: time::Instant) -> Check {
+        let mut windows = match self.windows.lock() {
+            Ok(windows) => windows,
+            Err(error) => error.into_inner(),
+        };
+
+        if windows.len() >= CLEANUP_THRESHOLD {
+            //Every command uses at most a few minutes, so there is no point keeping older entries.
+            windows.retain(|_, window| now.saturating_duration_since(window.start) < MAX_PERIOD);
+        }
+
+        let window = windows.entry(key).or_insert(Window {
+            start: now,
+            used: 0,
+            notified: false,
+        });
+
+        let elapsed = now.saturating_duration_since(window.start);
+        if elapsed >= bucket.period {
+            window.start = now;
+            window.used = 0;
+            window.notified = false;
+        }
+
+        if window.used < bucket.uses {
+            window.used += 1;
+            Check::Allowed
+        } else {
+            let notify = !window.notified;
+            window.notified = true;
+            Check::Limited {
+                retry: bucket.period - now.saturating_duration_since(window.start),
+                notify,
+            }
+        }
+    }
+}
+
+impl Handler {
+    ///Returns command's limit with server's overrides applied.
+    fn server_bucket(&self, server: u64, command: &Command) -> Option<Bucket> {
+        let bucket = match self.state.db.scan::<data::RateLimit, _>((server, command.id())).next() {
+            Some((_, limit)) if limit.uses == 0 => None,
+            //Override keeps command's strictness
+            Some((_, limit)) => Some(Bucket {
+                uses: limit.uses,
+                period: time::Duration::from_secs(limit.period),
+                mod_exempt: command.limit.map(|bucket| bucket.mod_exempt).unwrap_or(true),
+            }),
+            None => command.limit,
+        }?;
+
+        match self.state.db.get::<data::Server>(server) {
+            Ok(server) if server.limit_mods => Some(Bucket {
+                mod_exempt: false,
+                ..bucket
+            }),
+            _ => Some(bucket),
+        }
+    }
+
+    ///Checks whether command can be executed without exceeding its limit.
+    ///
+    ///Notifies user once, when limit is reached, and silently drops further attempts.
+    pub async fn check_rate_limit(&self, ctx: &HandlerContext<'_>, command: &Command) -> bool {
+        let bucket = match ctx.guild_id {
+            Some(server) => self.server_bucket(server.0, command),
+            None => command.limit,
+        };
+        let bucket = match bucket {
+            Some(bucket) if !(bucket.mod_exempt && ctx.is_mod) => bucket,
+            _ => return true,
+        };
+
+        let key = (ctx.guild_id.map(|id| id.0).unwrap_or(0), ctx.author.id.0, command.id());
+        match self.limits.check(key, bucket, time::Instant::now()) {
+            Check::Allowed => true,
+            Check::Limited { retry, notify } => {
+                let secs = retry.as_secs() + (retry.subsec_nanos() > 0) as u64;
+                let text = ctx.tr_with(Msg::RateLimited, &[("command", &command.name), ("secs", &secs)]);
+                let result = match ctx.source {
+                    context::Source::Message(_) if notify => ctx.reply(text).await,
+                    context::Source::Message(_) => Ok(()),
+                    //Slash command has to be responded always, but it is private anyway
+                    context::Source::Interaction(..) => ctx.reply_private(text).await,
+                };
+
+                if let Err(error) = result {
+                    rogu::warn!("Unable to notify about rate limit: {}", error);
+                }
+                false
+            },
+        }
+    }
+}
+
+impl Handler {
+    #[inline]
+    pub async fn handle_ratelimit(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let id = match ctx.guild_id {
+            Some(server) => server.0,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let command = match args.word() {
+            None => {
+                let limit_mods = self.state.db.get::<data::Server>(id).map(|server| server.limit_mods).unwrap_or(false);
+
+                let mut limits = String::new();
+                for command in registry::COMMANDS {
+                    if let Some((_, limit)) = self.state.db.scan::<data::RateLimit, _>((id, command.id())).next() {
+                        let _ = match limit.uses {
+                            0 => writeln!(limits, "`{}` off", command.name),
+                            uses => writeln!(limits, "`{}` {} per {}", command.name, uses, crate::utils::format_duration(time::Duration::from_secs(limit.period))),
+                        };
+                    }
+                }
+                if limits.is_empty() {
+                    limits.push_str("Default");
+                }
+
+                ctx.reply_embed_private(|m| {
+                    m.title("Rate limits")
+                     .field("Overrides", limits, false)
+                     .field("Moderators exempt", !limit_mods, false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("exempt") => {
+                let limit_mods = match try_arg!(ctx, args.next::<&str>("exempt")) {
+                    "on" => false,
+                    "off" => true,
+                    _ => return ctx.reply_usage(ArgError::Invalid("exempt")).await,
+                };
+
+                let mut server = match self.state.db.get::<data::Server>(id) {
+                    Ok(server) => server,
+                    Err(error) => {
+                        rogu::error!("Unable to get server info: {}", error);
+                        let _ = ctx.react(emoji::KINSHI).await;
+                        return Ok(());
+                    }
+                };
+                server.limit_mods = limit_mods;
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put(id, &server)).await;
+                return ctx.react(emoji::OK).await;
+            },
+            Some(command) => match registry::find(command) {
+                Some(command) => command,
+                None => return ctx.reply(ctx.tr(Msg::NoSuchCommand)).await,
+            },
+        };
+        let key = (id, command.id());
+
+        let db = self.state.db.clone();
+        match try_arg!(ctx, args.next::<&str>("uses")) {
+            "reset" => {
+                let _ = tokio::task::spawn_blocking(move || db.delete::<data::RateLimit>(key)).await;
+            },
+            "off" => {
+                let limit = data::RateLimit::default();
+                let _ = tokio::task::spawn_blocking(move || db.put(key, &limit)).await;
+            },
+            uses => {
+                let uses = match uses.parse::<u16>() {
+                    Ok(uses) if uses > 0 => uses,
+                    _ => return ctx.reply_usage(ArgError::Invalid("uses")).await,
+                };
+                let period = match try_arg!(ctx, args.next::<time::Duration>("period")) {
+                    period if period.as_secs() > 0 && period <= MAX_PERIOD => period.as_secs(),
+                    _ => return ctx.reply_usage(ArgError::Invalid("period")).await,
+                };
+
+                let limit = data::RateLimit {
+                    uses,
+                    period,
+                };
+                let _ = tokio::task::spawn_blocking(move || db.put(key, &limit)).await;
+            },
+        }
+
+        ctx.react(emoji::OK).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_limit_uses_within_period() {
+        let limiter = RateLimiter::default();
+        let bucket = Bucket::new(2, 10);
+        let key = (1, 2, 3);
+        let now = time::Instant::now();
+
+        assert_eq!(limiter.check(key, bucket, now), Check::Allowed);
+        assert_eq!(limiter.check(key, bucket, now + time::Duration::from_secs(1)), Check::Allowed);
+        assert_eq!(limiter.check(key, bucket, now + time::Duration::from_secs(4)), Check::Limited {
+            retry: time::Duration::from_secs(6),
+            notify: true,
+        });
+        assert_eq!(limiter.check(key, bucket, now + time::Duration::from_secs(5)), Check::Limited {
+            retry: time::Duration::from_secs(5),
+            notify: false,
+        });
+        //Other users are not affected
+        assert_eq!(limiter.check((1, 3, 3), bucket, now + time::Duration::from_secs(5)), Check::Allowed);
+
+        assert_eq!(limiter.check(key, bucket, now + time::Duration::from_secs(10)), Check::Allowed);
+    }
+}
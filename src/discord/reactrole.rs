@@ -0,0 +1,285 @@
+//! Roles, given for reactions on message
+
+use super::*;
+use super::args::{Args, ArgError};
+use crate::data::{ReactionRole, RoleMode};
+
+use serenity::model::channel::{Reaction, ReactionType};
+use serenity::model::id::EmojiId;
+
+//Discord's limit on distinct reactions per message
+const MAX_REACTIONS: usize = 20;
+
+///Parses custom emoji as `<:name:id>` or unicode one.
+fn parse_emoji(text: &str) -> Option<ReactionType> {
+    if let Some(custom) = text.strip_prefix('<').and_then(|text| text.strip_suffix('>')) {
+        let mut parts = custom.split(':');
+        let animated = match parts.next()? {
+            "" => false,
+            "a" => true,
+            _ => return None,
+        };
+        let name = parts.next()?;
+        let id = parts.next()?.parse::<u64>().ok()?;
+
+        match parts.next() {
+            Some(_) => None,
+            None => Some(ReactionType::Custom {
+                animated,
+                id: EmojiId(id),
+                name: Some(name.to_owned()),
+            }),
+        }
+    } else if text.chars().any(|ch| !ch.is_ascii()) && !text.contains(char::is_whitespace) {
+        Some(ReactionType::Unicode(text.to_owned()))
+    } else {
+        None
+    }
+}
+
+///Returns storage key of emoji.
+fn emoji_key(emoji: &ReactionType) -> Option<u64> {
+    match emoji {
+        ReactionType::Custom { id, .. } => Some(id.0),
+        //Variation selector is optional in user's input, but may be present in reaction
+        ReactionType::Unicode(text) => Some(xxhash_rust::xxh3::xxh3_64(text.replace('\u{FE0F}', "").as_bytes())),
+        _ => None,
+    }
+}
+
+///Parses message's link, `channel-message` pair or message id, returning channel if present.
+fn parse_message(text: &str) -> Option<(Option<u64>, u64)> {
+    if let Some(idx) = text.find("/channels/") {
+        let mut parts = text[idx + "/channels/".len()..].split('/');
+        let _server = parts.next()?;
+        let channel = parts.next()?.parse::<u64>().ok()?;
+        let message = parts.next()?.parse::<u64>().ok()?;
+        Some((Some(channel), message))
+    } else if let Some((channel, message)) = text.split_once('-') {
+        Some((Some(channel.parse().ok()?), message.parse().ok()?))
+    } else {
+        text.parse().ok().map(|message| (None, message))
+    }
+}
+
+impl Handler {
+    ///Gives or takes role, bound to reaction.
+    pub(super) async fn handle_reaction_role(&self, ctx: &Context, reaction: &Reaction, is_add: bool) {
+        let (server, user) = match (reaction.guild_id, reaction.user_id) {
+            (Some(server), Some(user)) if user.0 != self.state.info.id => (server, user),
+            _ => return,
+        };
+        let emoji = match emoji_key(&reaction.emoji) {
+            Some(emoji) => emoji,
+            None => return,
+        };
+
+        let binding = match self.state.db.get::<ReactionRole>((server.0, reaction.message_id.0, emoji)) {
+            Ok(binding) if binding.role != 0 => binding,
+            Ok(_) => return,
+            Err(error) => {
+                rogu::error!("Unable to get reaction role: {}", error);
+                return;
+            }
+        };
+
+        let result = match (is_add, binding.mode) {
+            (true, _) => ctx.http.add_member_role(server.0, user.0, binding.role).await,
+            (false, RoleMode::Verify) => Ok(()),
+            (false, _) => ctx.http.remove_member_role(server.0, user.0, binding.role).await,
+        };
+        if let Err(error) = result {
+            rogu::warn!("Unable to change reaction role {} of user {}: {}", binding.role, user.0, error);
+            return;
+        }
+
+        if is_add && binding.mode == RoleMode::Unique {
+            //Member is only present on addition, otherwise each role is checked
+            let roles = reaction.member.as_ref().map(|member| &member.roles);
+            let others = self.state.db.scan::<ReactionRole, _>((server.0, reaction.message_id.0)).filter(|(_, other)| other.role != binding.role);
+
+            for (_, other) in others {
+                if roles.map(|roles| roles.contains(&RoleId(other.role))).unwrap_or(true) {
+                    if let Err(error) = ctx.http.remove_member_role(server.0, user.0, other.role).await {
+                        rogu::warn!("Unable to remove reaction role {} of user {}: {}", other.role, user.0, error);
+                    }
+                }
+                //Stale reactions are removed too, so that only one is left
+                if let Some(other) = parse_emoji(&other.emoji) {
+                    let _ = reaction.channel_id.delete_reaction(&ctx.http, reaction.message_id, Some(user), other).await;
+                }
+            }
+        }
+    }
+
+    ///Removes reaction roles of deleted message.
+    pub(super) async fn forget_reaction_roles(&self, server: GuildId, message: MessageId) {
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || {
+            let keys: Vec<_> = db.scan::<ReactionRole, _>((server.0, message.0)).map(|(key, _)| key).collect();
+            for key in keys {
+                db.delete::<ReactionRole>(key);
+            }
+        }).await;
+    }
+
+    #[inline]
+    pub async fn handle_reactrole(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+        let action = try_arg!(ctx, args.next::<&str>("action"));
+
+        let guild = match ctx.guild().await {
+            Some(guild) => guild,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        if action == "list" {
+            let mut text = String::new();
+            for ((_, message, _), binding) in self.state.db.scan::<ReactionRole, _>(guild.id.0) {
+                text.push_str(&format!("- {} {} `{}` [message](https://discord.com/channels/{}/{}/{})\n", binding.emoji, RoleId(binding.role).mention(), binding.mode.name(), guild.id.0, binding.channel, message));
+            }
+
+            if text.is_empty() {
+                return ctx.reply(ctx.tr(Msg::NoReactionRoles)).await;
+            }
+
+            ctx.reply_embed_private(|m| m.title("Reaction roles").description(crate::utils::truncate(&text, 4096))).await?;
+            return ctx.react(emoji::OK).await;
+        }
+
+        let (channel, message) = match parse_message(try_arg!(ctx, args.next::<&str>("message"))) {
+            Some((channel, message)) => (channel.map(ChannelId).unwrap_or(ctx.channel_id), MessageId(message)),
+            None => return ctx.reply_usage(ArgError::Invalid("message")).await,
+        };
+        if !guild.channels.contains_key(&channel) {
+            return ctx.reply(ctx.tr(Msg::TargetNotFound)).await;
+        }
+
+        match action {
+            "add" => {
+                let text = try_arg!(ctx, args.next::<&str>("emoji"));
+                let (emoji, key) = match parse_emoji(text) {
+                    Some(emoji) if text.len() <= ReactionRole::MAX_EMOJI_LEN => match emoji_key(&emoji) {
+                        Some(key) => (emoji, key),
+                        None => return ctx.reply_usage(ArgError::Invalid("emoji")).await,
+                    },
+                    _ => return ctx.reply_usage(ArgError::Invalid("emoji")).await,
+                };
+                let role = match super::utils::find_role(try_arg!(ctx, args.text("role")), &guild.roles) {
+                    Some(role) => role,
+                    None => return ctx.reply(ctx.tr(Msg::RoleNotFound)).await,
+                };
+                if !super::utils::can_give_role(&guild, role, ctx.author.id, ctx.roles, UserId(self.state.info.id)) {
+                    return ctx.reply(ctx.tr(Msg::RoleTooHigh)).await;
+                }
+
+                let bindings: Vec<_> = self.state.db.scan::<ReactionRole, _>((guild.id.0, message.0)).collect();
+                if bindings.len() >= MAX_REACTIONS && !bindings.iter().any(|((_, _, other), _)| *other == key) {
+                    return ctx.reply(ctx.tr_with(Msg::TooManyReactionRoles, &[("max", &MAX_REACTIONS)])).await;
+                }
+
+                if let Err(error) = channel.message(&ctx.serenity.http, message).await {
+                    rogu::info!("Unable to get message {} for reaction role: {}", message.0, error);
+                    return ctx.reply(ctx.tr(Msg::MessageNotFound)).await;
+                }
+                //Unknown or inaccessible custom emoji fails here
+                if let Err(error) = channel.create_reaction(&ctx.serenity.http, message, emoji).await {
+                    rogu::info!("Unable to react with {}: {}", text, error);
+                    return ctx.reply_usage(ArgError::Invalid("emoji")).await;
+                }
+
+                let binding = ReactionRole {
+                    channel: channel.0,
+                    role: role.0,
+                    //All roles of message share mode
+                    mode: bindings.first().map(|(_, binding)| binding.mode).unwrap_or_default(),
+                    emoji: text.to_owned(),
+                };
+                let db = self.state.db.clone();
+                let key = (guild.id.0, message.0, key);
+                let _ = tokio::task::spawn_blocking(move || db.put(key, &binding)).await;
+            },
+            "remove" => {
+                let key = match parse_emoji(try_arg!(ctx, args.next::<&str>("emoji"))).as_ref().and_then(emoji_key) {
+                    Some(key) => (guild.id.0, message.0, key),
+                    None => return ctx.reply_usage(ArgError::Invalid("emoji")).await,
+                };
+
+                let binding = match self.state.db.get::<ReactionRole>(key) {
+                    Ok(binding) if binding.role != 0 => binding,
+                    _ => return ctx.reply(ctx.tr(Msg::ReactionRoleNotFound)).await,
+                };
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.delete::<ReactionRole>(key)).await;
+
+                //Message might be already gone, so reaction is best effort
+                if let Some(emoji) = parse_emoji(&binding.emoji) {
+                    let _ = channel.delete_reaction(&ctx.serenity.http, message, None, emoji).await;
+                }
+            },
+            "mode" => {
+                let mode = match RoleMode::from_name(try_arg!(ctx, args.next::<&str>("mode"))) {
+                    Some(mode) => mode,
+                    None => return ctx.reply_usage(ArgError::Invalid("mode")).await,
+                };
+
+                let db = self.state.db.clone();
+                let server = guild.id.0;
+                let count = tokio::task::spawn_blocking(move || {
+                    let bindings: Vec<_> = db.scan::<ReactionRole, _>((server, message.0)).collect();
+                    let count = bindings.len();
+                    for (key, mut binding) in bindings {
+                        binding.mode = mode;
+                        db.put(key, &binding);
+                    }
+                    count
+                }).await.unwrap_or(0);
+
+                if count == 0 {
+                    return ctx.reply(ctx.tr(Msg::ReactionRoleNotFound)).await;
+                }
+            },
+            _ => return ctx.reply_usage(ArgError::Invalid("action")).await,
+        }
+
+        ctx.react(emoji::OK).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_emoji() {
+        assert_eq!(parse_emoji("👍"), Some(ReactionType::Unicode("👍".to_owned())));
+        assert_eq!(parse_emoji("<:kinshi:123>"), Some(ReactionType::Custom { animated: false, id: EmojiId(123), name: Some("kinshi".to_owned()) }));
+        assert_eq!(parse_emoji("<a:dance:5>"), Some(ReactionType::Custom { animated: true, id: EmojiId(5), name: Some("dance".to_owned()) }));
+        assert_eq!(parse_emoji("<b:dance:5>"), None);
+        assert_eq!(parse_emoji("<:dance:x>"), None);
+        assert_eq!(parse_emoji("smile"), None);
+        assert_eq!(parse_emoji(""), None);
+    }
+
+    #[test]
+    fn should_ignore_variation_selector_in_emoji_key() {
+        let plain = emoji_key(&ReactionType::Unicode("\u{2764}".to_owned()));
+        let selected = emoji_key(&ReactionType::Unicode("\u{2764}\u{FE0F}".to_owned()));
+        assert_eq!(plain, selected);
+        assert_ne!(plain, emoji_key(&ReactionType::Unicode("👍".to_owned())));
+    }
+
+    #[test]
+    fn should_parse_message_reference() {
+        assert_eq!(parse_message("https://discord.com/channels/1/2/3"), Some((Some(2), 3)));
+        assert_eq!(parse_message("https://ptb.discordapp.com/channels/1/2/3"), Some((Some(2), 3)));
+        assert_eq!(parse_message("2-3"), Some((Some(2), 3)));
+        assert_eq!(parse_message("3"), Some((None, 3)));
+        assert_eq!(parse_message("https://discord.com/channels/1/2"), None);
+        assert_eq!(parse_message("message"), None);
+    }
+}
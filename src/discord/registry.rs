@@ -0,0 +1,658 @@
+//! Command declarations
+
+use super::{Handler, HandlerContext};
+use super::ratelimit::Bucket;
+
+use core::future::Future;
+use core::pin::Pin;
+
+pub(super) type CommandFuture<'a> = Pin<Box<dyn Future<Output = serenity::Result<()>> + Send + 'a>>;
+pub(super) type CommandHandler = for<'a> fn(&'a Handler, HandlerContext<'a>) -> CommandFuture<'a>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Who can execute command by default.
+pub(super) enum Access {
+    Everyone,
+    ///Moderators only, unless permitted by server's rules.
+    Mod,
+    ///Moderators only, regardless of server's rules.
+    Locked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Type of slash command's option.
+pub(super) enum OptionKind {
+    Text,
+    Integer,
+    User,
+    Role,
+    Channel,
+}
+
+///Slash command's option, passed to command in order of declaration.
+pub(super) struct SlashOption {
+    pub(super) name: &'static str,
+    pub(super) description: &'static str,
+    pub(super) kind: OptionKind,
+    pub(super) required: bool,
+}
+
+macro_rules! opt {
+    ($name:literal: Option<$kind:ident>, $description:literal) => {
+        SlashOption {
+            name: $name,
+            description: $description,
+            kind: OptionKind::$kind,
+            required: false,
+        }
+    };
+    ($name:literal: $kind:ident, $description:literal) => {
+        SlashOption {
+            name: $name,
+            description: $description,
+            kind: OptionKind::$kind,
+            required: true,
+        }
+    };
+}
+
+pub(super) struct Command {
+    pub(super) name: &'static str,
+    pub(super) aliases: &'static [&'static str],
+    pub(super) usage: &'static str,
+    pub(super) description: &'static str,
+    pub(super) access: Access,
+    ///Options of slash command, if command is available as such.
+    pub(super) slash: Option<&'static [SlashOption]>,
+    ///Limit on number of uses per user.
+    pub(super) limit: Option<Bucket>,
+    pub(super) handler: CommandHandler,
+}
+
+impl Command {
+    #[inline]
+    ///Returns unique id, that is used to store command's settings.
+    pub(super) fn id(&self) -> u64 {
+        xxhash_rust::xxh3::xxh3_64(self.name.as_bytes())
+    }
+
+    #[inline]
+    fn names(&self) -> impl Iterator<Item = &'static str> {
+        core::iter::once(self.name).chain(self.aliases.iter().copied())
+    }
+}
+
+macro_rules! handler {
+    ($method:ident) => {{
+        fn handler<'a>(handler: &'a Handler, ctx: HandlerContext<'a>) -> CommandFuture<'a> {
+            Box::pin(handler.$method(ctx))
+        }
+
+        handler
+    }}
+}
+
+pub(super) static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        aliases: &["commands"],
+        usage: "help",
+        description: "Asks for help. Sent in DM always.",
+        access: Access::Everyone,
+        slash: Some(&[]),
+        limit: Some(Bucket::new(1, 60)),
+        handler: handler!(handle_help),
+    },
+    Command {
+        name: "ping",
+        aliases: &[],
+        usage: "ping",
+        description: "Asks bot to reply back with `pong`.",
+        access: Access::Everyone,
+        slash: Some(&[]),
+        limit: Some(Bucket::new(3, 10)),
+        handler: handler!(handle_ping),
+    },
+    Command {
+        name: "roll",
+        aliases: &["r"],
+        usage: "roll <dice>",
+        description: "Asks to roll DnD dice with provided input. E.g. `1d4+1`.",
+        access: Access::Everyone,
+        slash: Some(&[opt!("dice": Text, "Dice to roll, e.g. 1d4+1")]),
+        limit: Some(Bucket::new(5, 10)),
+        handler: handler!(handle_roll),
+    },
+    Command {
+        name: "judge",
+        aliases: &["choose"],
+        usage: "judge <choice> <choice>...",
+        description: "Selects randomly among choices. Needs at least 2.",
+        access: Access::Everyone,
+        slash: Some(&[opt!("choices": Text, "Choices separated by space")]),
+        limit: Some(Bucket::new(5, 10)),
+        handler: handler!(handle_judge),
+    },
+    Command {
+        name: "verify",
+        aliases: &[],
+        usage: "verify [answer]",
+        description: "Passes verification gate, answering its question if there is one.",
+        access: Access::Everyone,
+        slash: Some(&[opt!("answer": Option<Text>, "Answer to gate's question")]),
+        limit: Some(Bucket::new(3, 60)),
+        handler: handler!(handle_verify),
+    },
+    Command {
+        name: "player",
+        aliases: &["music"],
+        usage: "player <start <link>|stop>",
+        description: "Controls music player. Starting costs 5 coins, stopping is for moderators.",
+        access: Access::Everyone,
+        slash: Some(&[opt!("command": Text, "start or stop"), opt!("link": Option<Text>, "Link to music to start")]),
+        limit: Some(Bucket::new(2, 30)),
+        handler: handler!(handle_player),
+    },
+    Command {
+        name: "whoami",
+        aliases: &["profile"],
+        usage: "whoami",
+        description: "Shows user's information privately.",
+        access: Access::Everyone,
+        slash: Some(&[]),
+        limit: Some(Bucket::new(2, 30)),
+        handler: handler!(handle_whoami),
+    },
+    Command {
+        name: "suggest",
+        aliases: &[],
+        usage: "suggest <text>",
+        description: "Ask to post a suggestion to developers. Costs 10 coins.",
+        access: Access::Everyone,
+        slash: Some(&[opt!("suggestion": Text, "Text of suggestion")]),
+        limit: Some(Bucket::new(1, 60).strict()),
+        handler: handler!(handle_suggest),
+    },
+    Command {
+        name: "allowance",
+        aliases: &["daily"],
+        usage: "allowance",
+        description: "Ask for allowance. Can be executed once per hour. Amount depends on level.",
+        access: Access::Everyone,
+        slash: Some(&[]),
+        limit: Some(Bucket::new(3, 60)),
+        handler: handler!(handle_allowance),
+    },
+    Command {
+        name: "config",
+        aliases: &[],
+        usage: "config",
+        description: "See current server config. Should be executed in server channel. Sent privately.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_config),
+    },
+    Command {
+        name: "shutdown",
+        aliases: &[],
+        usage: "shutdown",
+        description: "Performs graceful shutdown, saving database and stopping.",
+        access: Access::Locked,
+        slash: None,
+        limit: None,
+        handler: handler!(handle_shutdown),
+    },
+    Command {
+        name: "restart",
+        aliases: &[],
+        usage: "restart",
+        description: "Asks to perform restart, refreshing connection to discord and saving database.",
+        access: Access::Locked,
+        slash: None,
+        limit: None,
+        handler: handler!(handle_restart),
+    },
+    Command {
+        name: "set_dev",
+        aliases: &[],
+        usage: "set_dev",
+        description: "Tells to mark current channel as dev channel. Repeat to unset.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_set_dev),
+    },
+    Command {
+        name: "set_voice",
+        aliases: &["set_music"],
+        usage: "set_voice",
+        description: "Tells to use voice channel, that you're in, for voice. Repeat to unset.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_set_voice),
+    },
+    Command {
+        name: "set_welcome",
+        aliases: &[],
+        usage: "set_welcome",
+        description: "Tells to use current channel to welcome new users. Repeat to unset.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_set_welcome),
+    },
+    Command {
+        name: "welcome",
+        aliases: &[],
+        usage: "welcome [preview] | welcome position <x> <y> [width] | welcome avatar <<x> <y> <size>|off> | welcome size <px> | welcome color <#rrggbb> | welcome <text|message|dm> <template|reset> | welcome background [reset]",
+        description: "Customizes welcome card and private message to new members. Background is uploaded as attachment of the command's message.",
+        access: Access::Mod,
+        slash: Some(&[opt!("setting": Option<Text>, "preview, position, avatar, size, color, text, message, dm or background"), opt!("value": Option<Text>, "New value of setting")]),
+        limit: None,
+        handler: handler!(handle_welcome),
+    },
+    Command {
+        name: "goodbye",
+        aliases: &[],
+        usage: "goodbye [preview] | goodbye channel <channel|off> | goodbye image <on|off> | goodbye message <template|reset> | goodbye cleanup <delay|off>",
+        description: "Configures goodbye of members, who left. Cleanup removes their permission rules after delay, unless they return.",
+        access: Access::Mod,
+        slash: Some(&[opt!("setting": Option<Text>, "preview, channel, image, message or cleanup"), opt!("value": Option<Text>, "New value of setting")]),
+        limit: None,
+        handler: handler!(handle_goodbye),
+    },
+    Command {
+        name: "set_spam",
+        aliases: &[],
+        usage: "set_spam",
+        description: "Tells to use current channel for bot's announcements. Repeat to unset.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_set_spam),
+    },
+    Command {
+        name: "set_modlog",
+        aliases: &[],
+        usage: "set_modlog",
+        description: "Tells to use current channel for audit log of server's events. Repeat to unset.",
+        access: Access::Mod,
+        slash: Some(&[]),
+        limit: None,
+        handler: handler!(handle_set_modlog),
+    },
+    Command {
+        name: "exp_event",
+        aliases: &[],
+        usage: "exp_event <rate> <duration> [delay] | exp_event off",
+        description: "Schedules exp multiplier event, e.g. `exp_event 2 48h`.",
+        access: Access::Mod,
+        slash: Some(&[opt!("rate": Text, "Multiplier, e.g. 1.5, or off"), opt!("duration": Option<Text>, "Duration, e.g. 48h"), opt!("delay": Option<Text>, "Delay before start, e.g. 1d")]),
+        limit: None,
+        handler: handler!(handle_exp_event),
+    },
+    Command {
+        name: "exp_weight",
+        aliases: &[],
+        usage: "exp_weight [weight]",
+        description: "Sets exp weight of current channel, e.g. `exp_weight 0` or `exp_weight 1.5`. Applies to voice too, when used in voice channel's chat. Shows current weight without argument.",
+        access: Access::Mod,
+        slash: Some(&[opt!("weight": Option<Text>, "Multiplier, e.g. 1.5")]),
+        limit: None,
+        handler: handler!(handle_exp_weight),
+    },
+    Command {
+        name: "levelup",
+        aliases: &[],
+        usage: "levelup [dest <spam|channel|dm|off> | image <on|off> | message <text|reset>]",
+        description: "Configures level up announcement. Message supports `{mention}`, `{level}` and `{rank}` among server members. Shows settings without argument.",
+        access: Access::Mod,
+        slash: Some(&[opt!("option": Option<Text>, "dest, image or message"), opt!("value": Option<Text>, "New value of option")]),
+        limit: None,
+        handler: handler!(handle_level_up),
+    },
+    Command {
+        name: "prefix",
+        aliases: &[],
+        usage: "prefix [prefix|reset]",
+        description: "Sets command prefix of server. Mentioning bot always works as prefix. Shows current prefix without argument.",
+        access: Access::Mod,
+        slash: Some(&[opt!("prefix": Option<Text>, "New prefix or reset")]),
+        limit: None,
+        handler: handler!(handle_prefix),
+    },
+    Command {
+        name: "lang",
+        aliases: &["language"],
+        usage: "lang [en|ja]",
+        description: "Sets language of bot's responses on server. Shows current language without argument.",
+        access: Access::Mod,
+        slash: Some(&[opt!("lang": Option<Text>, "Language code: en or ja")]),
+        limit: None,
+        handler: handler!(handle_lang),
+    },
+    Command {
+        name: "modrole",
+        aliases: &[],
+        usage: "modrole <add|remove> <role>",
+        description: "Adds or removes moderator role. Once set, roles are no longer detected by name. Administrators are always moderators.",
+        access: Access::Locked,
+        slash: Some(&[opt!("action": Text, "add or remove"), opt!("role": Role, "Moderator role")]),
+        limit: None,
+        handler: handler!(handle_mod_role),
+    },
+    Command {
+        name: "perm",
+        aliases: &[],
+        usage: "perm <allow|deny> <command> <target> | perm reset <command> [target] | perm list",
+        description: "Allows or denies command per role, channel or user. Rules apply in order: user, channel's deny, role, channel's allow, @everyone.",
+        access: Access::Locked,
+        slash: Some(&[opt!("action": Text, "allow, deny, reset or list"), opt!("command": Option<Text>, "Command's name"), opt!("target": Option<Text>, "Role, channel or user")]),
+        limit: None,
+        handler: handler!(handle_perm),
+    },
+    Command {
+        name: "ratelimit",
+        aliases: &[],
+        usage: "ratelimit [<command> <uses> <period>] | ratelimit <command> <off|reset> | ratelimit exempt <on|off>",
+        description: "Overrides how many times command can be used per period, up to 1h. Moderators are exempt from non-strict limits, unless turned off. Shows overrides without arguments.",
+        access: Access::Mod,
+        slash: Some(&[opt!("command": Option<Text>, "Command's name or exempt"), opt!("uses": Option<Text>, "Uses per period, off, reset, or on/off for exempt"), opt!("period": Option<Text>, "Period of limit, e.g. 30s")]),
+        limit: None,
+        handler: handler!(handle_ratelimit),
+    },
+    Command {
+        name: "automod",
+        aliases: &[],
+        usage: "automod [<filter> <off|delete|warn|mute> [limit]] | automod duration <duration>",
+        description: "Configures auto-moderation filters: words, invites, links, caps, mentions, emoji and spam. Shows settings without arguments.",
+        access: Access::Mod,
+        slash: Some(&[opt!("filter": Option<Text>, "Filter's name or duration"), opt!("action": Option<Text>, "off, delete, warn, mute or duration of mute"), opt!("limit": Option<Integer>, "Filter's threshold")]),
+        limit: None,
+        handler: handler!(handle_automod),
+    },
+    Command {
+        name: "blocklist",
+        aliases: &[],
+        usage: "blocklist <add|regex|remove> <pattern> | blocklist list",
+        description: "Manages words and regex patterns, blocked by `words` filter.",
+        access: Access::Mod,
+        slash: Some(&[opt!("action": Text, "add, regex, remove or list"), opt!("pattern": Option<Text>, "Word or regex pattern")]),
+        limit: None,
+        handler: handler!(handle_blocklist),
+    },
+    Command {
+        name: "exempt",
+        aliases: &[],
+        usage: "exempt <channel|role>",
+        description: "Exempts channel or role from auto-moderation. Repeat to remove exemption.",
+        access: Access::Mod,
+        slash: Some(&[opt!("target": Text, "Channel or role")]),
+        limit: None,
+        handler: handler!(handle_exempt),
+    },
+    Command {
+        name: "raid",
+        aliases: &[],
+        usage: "raid [start|lift] | raid rate <joins> [secs] | raid age <days> | raid action <none|kick|quarantine> | raid role <role|reset>",
+        description: "Configures raid protection, that turns on raid mode on join spikes or young accounts. Shows settings without arguments.",
+        access: Access::Mod,
+        slash: Some(&[opt!("setting": Option<Text>, "start, lift, rate, age, action or role"), opt!("value": Option<Text>, "New value of setting"), opt!("secs": Option<Integer>, "Period of join rate in seconds")]),
+        limit: None,
+        handler: handler!(handle_raid),
+    },
+    Command {
+        name: "warn",
+        aliases: &[],
+        usage: "warn <user> [reason]",
+        description: "Warns user, sending reason in DM.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to warn"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_warn),
+    },
+    Command {
+        name: "kick",
+        aliases: &[],
+        usage: "kick <user> [reason]",
+        description: "Kicks user from server.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to kick"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_kick),
+    },
+    Command {
+        name: "ban",
+        aliases: &[],
+        usage: "ban <user> [duration] [reason]",
+        description: "Bans user from server, optionally for limited time, e.g. `ban @user 7d spam`.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to ban"), opt!("duration": Option<Text>, "Duration, e.g. 7d"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_ban),
+    },
+    Command {
+        name: "unban",
+        aliases: &[],
+        usage: "unban <user> [reason]",
+        description: "Lifts ban of user.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to unban"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_unban),
+    },
+    Command {
+        name: "mute",
+        aliases: &["timeout"],
+        usage: "mute <user> [duration] [reason]",
+        description: "Gives mute role to user, optionally for limited time, e.g. `mute @user 1h`.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to mute"), opt!("duration": Option<Text>, "Duration, e.g. 1h"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_mute),
+    },
+    Command {
+        name: "unmute",
+        aliases: &[],
+        usage: "unmute <user> [reason]",
+        description: "Removes mute role from user.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to unmute"), opt!("reason": Option<Text>, "Reason")]),
+        limit: None,
+        handler: handler!(handle_unmute),
+    },
+    Command {
+        name: "purge",
+        aliases: &["clear"],
+        usage: "purge <count> [user|bots|contains:<text>|attachments]",
+        description: "Deletes recent messages in current channel, optionally only matching ones.",
+        access: Access::Mod,
+        slash: Some(&[opt!("count": Integer, "Number of messages to delete"), opt!("filter": Option<Text>, "User, bots, contains:<text> or attachments")]),
+        limit: None,
+        handler: handler!(handle_purge),
+    },
+    Command {
+        name: "lock",
+        aliases: &[],
+        usage: "lock [channel]",
+        description: "Prevents everyone from sending messages in channel, current one by default.",
+        access: Access::Mod,
+        slash: Some(&[opt!("channel": Option<Channel>, "Channel to lock")]),
+        limit: None,
+        handler: handler!(handle_lock),
+    },
+    Command {
+        name: "unlock",
+        aliases: &[],
+        usage: "unlock [channel]",
+        description: "Restores channel's permissions as they were before lock.",
+        access: Access::Mod,
+        slash: Some(&[opt!("channel": Option<Channel>, "Channel to unlock")]),
+        limit: None,
+        handler: handler!(handle_unlock),
+    },
+    Command {
+        name: "lockdown",
+        aliases: &[],
+        usage: "lockdown [lift] | lockdown <add|remove> <channel> | lockdown list",
+        description: "Locks all lockdown channels at once, or lifts lockdown. Manages list of lockdown channels.",
+        access: Access::Mod,
+        slash: Some(&[opt!("action": Option<Text>, "lift, add, remove or list"), opt!("channel": Option<Channel>, "Channel to add or remove")]),
+        limit: None,
+        handler: handler!(handle_lockdown),
+    },
+    Command {
+        name: "slowmode",
+        aliases: &[],
+        usage: "slowmode <duration|off> [channel]",
+        description: "Sets slowmode of channel, current one by default.",
+        access: Access::Mod,
+        slash: Some(&[opt!("duration": Text, "Delay between messages, e.g. 10s, or off"), opt!("channel": Option<Channel>, "Channel to change")]),
+        limit: None,
+        handler: handler!(handle_slowmode),
+    },
+    Command {
+        name: "reactrole",
+        aliases: &["rr"],
+        usage: "reactrole add <message> <emoji> <role> | reactrole remove <message> <emoji> | reactrole mode <message> <normal|unique|verify> | reactrole list",
+        description: "Binds roles to reactions on message. In unique mode only one role can be picked, in verify mode roles are only given.",
+        access: Access::Mod,
+        slash: Some(&[opt!("action": Text, "add, remove, mode or list"), opt!("message": Option<Text>, "Message's link or id"), opt!("emoji": Option<Text>, "Emoji, or normal, unique or verify for mode"), opt!("role": Option<Role>, "Role to give")]),
+        limit: None,
+        handler: handler!(handle_reactrole),
+    },
+    Command {
+        name: "autorole",
+        aliases: &[],
+        usage: "autorole [<add|remove> <role>]",
+        description: "Shows or changes roles, which are given to new members on join.",
+        access: Access::Mod,
+        slash: Some(&[opt!("action": Option<Text>, "add or remove"), opt!("role": Option<Role>, "Role to add or remove")]),
+        limit: None,
+        handler: handler!(handle_autorole),
+    },
+    Command {
+        name: "gate",
+        aliases: &[],
+        usage: "gate [channel <channel|off>] | gate role <role|reset> | gate question <question> | <answer> | gate question reset",
+        description: "Configures verification gate. New members get member role and welcome only after reacting or answering question in gate channel.",
+        access: Access::Mod,
+        slash: Some(&[opt!("setting": Option<Text>, "channel, role or question"), opt!("value": Option<Text>, "New value of setting")]),
+        limit: None,
+        handler: handler!(handle_gate),
+    },
+    Command {
+        name: "muterole",
+        aliases: &[],
+        usage: "muterole <role|reset>",
+        description: "Sets role, which is given to muted users.",
+        access: Access::Mod,
+        slash: Some(&[opt!("role": Text, "Mute role or reset")]),
+        limit: None,
+        handler: handler!(handle_mute_role),
+    },
+    Command {
+        name: "cases",
+        aliases: &[],
+        usage: "cases <user>",
+        description: "Lists moderation cases of user.",
+        access: Access::Mod,
+        slash: Some(&[opt!("user": User, "User to look up")]),
+        limit: None,
+        handler: handler!(handle_cases),
+    },
+    Command {
+        name: "case",
+        aliases: &[],
+        usage: "case <number> [reason <text>]",
+        description: "Shows moderation case or amends its reason.",
+        access: Access::Mod,
+        slash: Some(&[opt!("case": Integer, "Case number"), opt!("option": Option<Text>, "reason"), opt!("reason": Option<Text>, "New reason")]),
+        limit: None,
+        handler: handler!(handle_case),
+    },
+];
+
+///Looks up command by its name or alias.
+pub(super) fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.names().any(|command| command.eq_ignore_ascii_case(name)))
+}
+
+///Looks up command with the most similar name, if any is close enough.
+pub(super) fn suggest(name: &str) -> Option<&'static Command> {
+    //Allow up to 2 typos, but no more than third of the name
+    let max_distance = core::cmp::min(2, (name.chars().count() + 2) / 3);
+
+    COMMANDS.iter().filter_map(|command| {
+        command.names().map(|alias| distance(alias, name)).min().map(|distance| (distance, command))
+    }).filter(|(distance, _)| *distance <= max_distance).min_by_key(|(distance, _)| *distance).map(|(_, command)| command)
+}
+
+//Levenshtein distance
+fn distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().flat_map(char::to_lowercase).collect();
+    let mut row: Vec<usize> = (0..=right.len()).collect();
+
+    for (idx, left_ch) in left.chars().flat_map(char::to_lowercase).enumerate() {
+        let mut prev_diag = row[0];
+        row[0] = idx + 1;
+
+        for (right_idx, right_ch) in right.iter().enumerate() {
+            let substitution = prev_diag + (left_ch != *right_ch) as usize;
+            prev_diag = row[right_idx + 1];
+            row[right_idx + 1] = core::cmp::min(substitution, core::cmp::min(row[right_idx], row[right_idx + 1]) + 1);
+        }
+    }
+
+    row[right.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_calculate_distance() {
+        assert_eq!(distance("roll", "roll"), 0);
+        assert_eq!(distance("roll", "ROLL"), 0);
+        assert_eq!(distance("roll", "rol"), 1);
+        assert_eq!(distance("roll", "rool"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "ping"), 4);
+    }
+
+    #[test]
+    fn verify_commands() {
+        let mut names = std::collections::HashSet::new();
+        for command in COMMANDS {
+            assert!(command.usage.starts_with(command.name), "Usage of '{}' should start with its name", command.name);
+            for name in command.names() {
+                assert!(names.insert(name), "'{}' is declared twice", name);
+            }
+
+            if let Some(options) = command.slash {
+                assert!(command.name.len() <= 32, "Slash command '{}' name is too long", command.name);
+                //Discord requires mandatory options to go first
+                let optional = options.iter().position(|option| !option.required).unwrap_or(options.len());
+                assert!(options[optional..].iter().all(|option| !option.required), "'{}' has required option after optional", command.name);
+                for option in options {
+                    assert!(option.name.len() <= 32 && option.name.chars().all(|ch| ch.is_ascii_lowercase()), "'{}' has invalid option name '{}'", command.name, option.name);
+                    assert!(option.description.len() <= 100, "'{}' has too long option description", command.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn should_find_command() {
+        assert_eq!(find("roll").expect("find roll").name, "roll");
+        assert_eq!(find("R").expect("find alias").name, "roll");
+        assert!(find("rol").is_none());
+        assert_eq!(suggest("rol").expect("suggest roll").name, "roll");
+        assert_eq!(suggest("whoamii").expect("suggest whoami").name, "whoami");
+        assert_eq!(suggest("alowance").expect("suggest allowance").name, "allowance");
+        assert!(suggest("something").is_none());
+    }
+}
@@ -0,0 +1,209 @@
+//! Lifts temporary bans and mutes, cleans up after departed members
+
+use super::*;
+use crate::data::{CaseAction, Departure, Expiry, Permission, PermissionTarget};
+
+use serenity::http::client::Http;
+use tokio::sync::mpsc;
+
+use std::sync::Arc;
+use std::time;
+
+//Upper limit of sleep, in case of clock adjustments
+const MAX_SLEEP: time::Duration = time::Duration::from_secs(10 * 60);
+
+pub type SchedulerSender = mpsc::Sender<SchedulerCommand>;
+
+pub enum SchedulerCommand {
+    ///Expirations are changed, re-read them.
+    Refresh,
+    Shutdown,
+}
+
+#[inline]
+fn now() -> u64 {
+    time::SystemTime::now().duration_since(time::SystemTime::UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or(0)
+}
+
+//Client errors mean there is nothing to lift anymore (e.g. user left) or we're not allowed to.
+fn is_permanent(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(error) => match error.as_ref() {
+            serenity::http::HttpError::UnsuccessfulRequest(response) => response.status_code.is_client_error(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+///Schedules end of ban or mute, replacing previous one.
+pub fn schedule(db: &DbView, server: u64, user: u64, expiry: Expiry, duration: time::Duration) {
+    cancel(db, server, user, expiry.action);
+    //Ban and mute may expire at the same time
+    db.put((now().saturating_add(duration.as_secs()), server, user, u64::from(expiry.action.to_u8())), &expiry);
+}
+
+///Cancels pending end of ban or mute, if any.
+pub fn cancel(db: &DbView, server: u64, user: u64, action: CaseAction) {
+    let pending: Vec<_> = db.entries::<Expiry>().filter(|((_, expiry_server, expiry_user, _), expiry)| {
+        *expiry_server == server && *expiry_user == user && expiry.action == action
+    }).map(|(key, _)| key).collect();
+
+    for key in pending {
+        db.delete::<Expiry>(key);
+    }
+}
+
+///Schedules removal of departed member's server data, replacing previous one.
+pub fn schedule_cleanup(db: &DbView, server: u64, user: u64, delay: time::Duration) {
+    cancel_cleanup(db, server, user);
+    let now = now();
+    db.put((now.saturating_add(delay.as_secs()), server, user), &Departure { time: now });
+}
+
+///Cancels pending removal of member's server data, if any.
+pub fn cancel_cleanup(db: &DbView, server: u64, user: u64) {
+    let pending: Vec<_> = db.entries::<Departure>().filter(|((_, departure_server, departure_user), _)| {
+        *departure_server == server && *departure_user == user
+    }).map(|(key, _)| key).collect();
+
+    for key in pending {
+        db.delete::<Departure>(key);
+    }
+}
+
+///Removes member's server data, returning number of removed records.
+///
+///Moderation cases are kept as server's history.
+pub fn clean_member(db: &DbView, server: u64, user: u64) -> usize {
+    let rules: Vec<_> = db.scan::<Permission, _>(server).filter(|((_, _, target), rule)| {
+        *target == user && rule.target == PermissionTarget::User
+    }).map(|(key, _)| key).collect();
+
+    for key in rules.iter() {
+        db.delete::<Permission>(*key);
+    }
+
+    rules.len()
+}
+
+pub struct Scheduler {
+    db: DbView,
+    http: Arc<Http>,
+    //Bot's id, recorded as moderator
+    id: u64,
+    receiver: mpsc::Receiver<SchedulerCommand>,
+}
+
+impl Scheduler {
+    pub fn new(db: DbView, http: Arc<Http>, id: u64) -> (Self, SchedulerSender) {
+        let (sender, receiver) = mpsc::channel(16);
+
+        (Self {
+            db,
+            http,
+            id,
+            receiver,
+        }, sender)
+    }
+
+    async fn lift(&self, server: u64, user: u64, expiry: &Expiry) -> serenity::Result<()> {
+        match expiry.action {
+            CaseAction::Ban => self.http.remove_ban(server, user).await,
+            CaseAction::Mute => self.http.remove_member_role(server, user, expiry.role).await,
+            action => {
+                rogu::warn!("Unexpected {} expiration", action.name());
+                Ok(())
+            }
+        }
+    }
+
+    //Lifts every due expiration, returning time of the next one.
+    async fn expire(&self) -> Option<u64> {
+        let now = now();
+
+        for (key, expiry) in self.db.entries::<Expiry>() {
+            let (time, server, user, _) = key;
+            if time > now {
+                return Some(time);
+            }
+
+            let action = match expiry.action {
+                CaseAction::Ban => CaseAction::Unban,
+                _ => CaseAction::Unmute,
+            };
+
+            match self.lift(server, user, &expiry).await {
+                Ok(()) => {
+                    let case = data::Case {
+                        action,
+                        target: user,
+                        moderator: self.id,
+                        time: now,
+                        duration: 0,
+                        reason: "Expired".to_owned(),
+                    };
+                    let db = self.db.clone();
+                    let _ = tokio::task::spawn_blocking(move || moderation::open_case(&db, server, &case)).await;
+                },
+                Err(error) if is_permanent(&error) => {
+                    rogu::warn!("Unable to {} user={} on server={}, giving up: {}", action.name(), user, server, error);
+                },
+                Err(error) => {
+                    rogu::error!("Unable to {} user={} on server={}, will retry: {}", action.name(), user, server, error);
+                    return Some(now + 60);
+                }
+            }
+
+            self.db.delete::<Expiry>(key);
+        }
+
+        None
+    }
+
+    //Cleans up after every due departure, returning time of the next one.
+    fn cleanup(&self) -> Option<u64> {
+        let now = now();
+
+        for ((time, server, user), departure) in self.db.entries::<Departure>() {
+            if time > now {
+                return Some(time);
+            }
+
+            //Cleanup might be turned off since departure
+            let is_enabled = self.db.get::<data::Server>(server).map(|server| server.leave_cleanup != 0).unwrap_or(false);
+            if is_enabled {
+                let count = clean_member(&self.db, server, user);
+                rogu::info!("Removed {} records of user={}, who left server={} at {}", count, user, server, departure.time);
+            }
+
+            self.db.delete::<Departure>((time, server, user));
+        }
+
+        None
+    }
+
+    pub async fn run(mut self) {
+        rogu::info!("Pending expirations: {}", self.db.entries::<Expiry>().count());
+        rogu::info!("Pending cleanups: {}", self.db.entries::<Departure>().count());
+
+        loop {
+            let next = match (self.expire().await, self.cleanup()) {
+                (Some(expiry), Some(departure)) => Some(core::cmp::min(expiry, departure)),
+                (expiry, departure) => expiry.or(departure),
+            };
+            let sleep = match next {
+                Some(next) => core::cmp::min(time::Duration::from_secs(next.saturating_sub(now())), MAX_SLEEP),
+                None => MAX_SLEEP,
+            };
+
+            tokio::select! {
+                cmd = self.receiver.recv() => match cmd {
+                    Some(SchedulerCommand::Refresh) => continue,
+                    Some(SchedulerCommand::Shutdown) | None => break,
+                },
+                _ = tokio::time::sleep(sleep) => (),
+            }
+        }
+    }
+}
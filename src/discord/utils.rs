@@ -19,3 +19,84 @@ impl crate::game::LevelExpModifier for Message {
         }
     }
 }
+
+///Returns command's text, if message starts with prefix or bot's mention.
+pub fn strip_command<'a>(text: &'a str, prefix: &str, bot_id: u64) -> Option<&'a str> {
+    if let Some(mention) = text.strip_prefix("<@") {
+        if let Some(end) = mention.find('>') {
+            if mention[..end].trim_start_matches('!').parse::<u64>() == Ok(bot_id) {
+                return Some(mention[end + 1..].trim_start());
+            }
+        }
+    }
+
+    match text.strip_prefix(prefix) {
+        //Repeated prefix is not a command, e.g. `...`
+        Some(cmd) if !cmd.starts_with(prefix) => Some(cmd.trim_start()),
+        _ => None,
+    }
+}
+
+///Finds role by mention, id or name.
+pub fn find_role(text: &str, roles: &HashMap<RoleId, Role>) -> Option<RoleId> {
+    let id = text.strip_prefix("<@&").and_then(|text| text.strip_suffix('>')).unwrap_or(text);
+
+    match id.parse::<u64>() {
+        Ok(id) => roles.get(&RoleId(id)).map(|role| role.id),
+        Err(_) => roles.values().find(|role| role.name.eq_ignore_ascii_case(text)).map(|role| role.id),
+    }
+}
+
+///Returns position of highest role among `roles`, @everyone being the lowest.
+fn top_position(roles: &[RoleId], guild_roles: &HashMap<RoleId, Role>) -> i64 {
+    roles.iter().filter_map(|role| guild_roles.get(role)).map(|role| role.position).max().unwrap_or(0)
+}
+
+///Checks whether role can be given out by bot on behalf of author.
+///
+///Role must be below highest roles of both bot and author, unless author is owner.
+///Roles, managed by integrations, and @everyone cannot be given at all.
+pub fn can_give_role(guild: &Guild, role: RoleId, author: UserId, author_roles: &[RoleId], bot: UserId) -> bool {
+    let role = match guild.roles.get(&role) {
+        //@everyone role has the same id as server
+        Some(role) if !role.managed && role.id.0 != guild.id.0 => role,
+        _ => return false,
+    };
+    let bot_roles = guild.members.get(&bot).map(|member| member.roles.as_slice()).unwrap_or(&[]);
+
+    role.position < top_position(bot_roles, &guild.roles) && (author == guild.owner_id || role.position < top_position(author_roles, &guild.roles))
+}
+
+///Returns creation time of discord's entity in seconds since epoch, as encoded in its snowflake id.
+#[inline]
+pub const fn snowflake_time(id: u64) -> u64 {
+    //Discord's epoch in milliseconds
+    const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+    ((id >> 22) + DISCORD_EPOCH) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_strip_command() {
+        assert_eq!(strip_command(".roll 1d4", ".", 1), Some("roll 1d4"));
+        assert_eq!(strip_command(". roll", ".", 1), Some("roll"));
+        assert_eq!(strip_command("...", ".", 1), None);
+        assert_eq!(strip_command("hello", ".", 1), None);
+        assert_eq!(strip_command("s!roll", "s!", 1), Some("roll"));
+        assert_eq!(strip_command("s!s!roll", "s!", 1), None);
+        assert_eq!(strip_command("<@1> roll", ".", 1), Some("roll"));
+        assert_eq!(strip_command("<@!1>roll", ".", 1), Some("roll"));
+        assert_eq!(strip_command("<@2> roll", ".", 1), None);
+        assert_eq!(strip_command("<@&1> roll", ".", 1), None);
+    }
+
+    #[test]
+    fn should_decode_snowflake_time() {
+        //Example snowflake from discord's documentation
+        assert_eq!(snowflake_time(175928847299117063), 1462015105);
+    }
+}
@@ -0,0 +1,177 @@
+use super::*;
+
+use std::time;
+
+//Interval of crediting ongoing sessions, so that restart loses at most that much
+pub const VOICE_TICK: time::Duration = time::Duration::from_secs(60);
+
+///Ongoing voice activity of member.
+pub struct VoiceSession {
+    //Start of not yet credited activity
+    start: time::Instant,
+    //Channel, which exp weight applies
+    channel: u64,
+}
+
+//Key is (server, user)
+pub type VoiceSessions = tokio::sync::Mutex<HashMap<(u64, u64), VoiceSession>>;
+
+///Returns minutes to credit for activity, which ended, dropping partial minute.
+///
+///Rounding up would reward rejoining every half a minute.
+fn session_minutes(duration: time::Duration) -> u32 {
+    (duration.as_secs() / 60) as u32
+}
+
+impl Handler {
+    ///Re-evaluates voice activity of every member on server.
+    ///
+    ///Member is considered active when he is in voice channel with at least one other person,
+    ///and he is neither muted nor deafened.
+    pub async fn update_voice_activity(&self, ctx: &Context, server: GuildId) {
+        let guild = match ctx.cache.guild(server).await {
+            Some(guild) => guild,
+            None => return,
+        };
+
+        let is_bot = |user: &UserId, state: &VoiceState| {
+            state.member.as_ref().or_else(|| guild.members.get(user)).map(|member| member.user.bot).unwrap_or(false)
+        };
+
+        //Number of people in channel, regardless of their mute status
+        let mut channels = HashMap::new();
+        for (user, state) in guild.voice_states.iter() {
+            match state.channel_id {
+                Some(channel) if Some(channel) != guild.afk_channel_id && !is_bot(user, state) => {
+                    *channels.entry(channel).or_insert(0usize) += 1;
+                },
+                _ => (),
+            }
+        }
+
+        let mut active = HashMap::new();
+        for (user, state) in guild.voice_states.iter() {
+            if state.mute || state.deaf || state.self_mute || state.self_deaf || is_bot(user, state) {
+                continue;
+            }
+
+            if let Some(channel) = state.channel_id {
+                if channels.get(&channel).map(|num| *num > 1).unwrap_or(false) {
+                    active.insert(user.0, channel.0);
+                }
+            }
+        }
+
+        let now = time::Instant::now();
+        let mut finished = Vec::new();
+        {
+            let mut sessions = self.voice.lock().await;
+            sessions.retain(|&(session_server, user), session| match session_server == server.0 && !active.contains_key(&user) {
+                true => {
+                    finished.push((user, session.channel, session_minutes(now.duration_since(session.start))));
+                    false
+                },
+                false => true,
+            });
+
+            for (user, channel) in active {
+                //Moving between channels continues session
+                sessions.entry((server.0, user)).or_insert(VoiceSession { start: now, channel }).channel = channel;
+            }
+        }
+
+        for (user, channel, minutes) in finished {
+            if let Err(error) = self.add_voice_time(ctx, server.0, channel, user, minutes).await {
+                rogu::error!("Failed to deliver message: {}", error);
+            }
+        }
+    }
+
+    ///Credits whole minutes of ongoing sessions, carrying over the rest.
+    pub async fn credit_voice_activity(&self, ctx: &Context) {
+        let now = time::Instant::now();
+        let mut credited = Vec::new();
+        {
+            let mut sessions = self.voice.lock().await;
+            for (&(server, user), session) in sessions.iter_mut() {
+                let minutes = now.duration_since(session.start).as_secs() / 60;
+                if minutes > 0 {
+                    session.start += time::Duration::from_secs(minutes * 60);
+                    credited.push((server, session.channel, user, minutes as u32));
+                }
+            }
+        }
+
+        for (server, channel, user, minutes) in credited {
+            if let Err(error) = self.add_voice_time(ctx, server, channel, user, minutes).await {
+                rogu::error!("Failed to deliver message: {}", error);
+            }
+        }
+    }
+
+    ///Credits voice minutes, with the same exp multipliers as chat.
+    async fn add_voice_time(&self, ctx: &Context, server_id: u64, channel: u64, id: u64, minutes: u32) -> serenity::Result<()> {
+        use game::LevelExpModifier;
+
+        if minutes == 0 {
+            return Ok(());
+        }
+
+        let mut user: data::User = match self.state.db.get(id) {
+            Ok(user) => user,
+            Err(error) => {
+                rogu::error!("Unable to get user data: {}", error);
+                return Ok(())
+            }
+        };
+
+        let server_info: data::Server = match self.state.db.get(server_id) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return Ok(());
+            }
+        };
+
+        let channel_info: data::Channel = match self.state.db.get(channel) {
+            Ok(channel_info) => channel_info,
+            Err(error) => {
+                rogu::error!("Unable to get channel info: {}", error);
+                return Ok(());
+            }
+        };
+
+        rogu::debug!("User={} spent {} minutes in voice on server={}", id, minutes, server_id);
+
+        let now = std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let rate = game::ExpRate(channel_info.exp_weight).compose(game::ExpRate(server_info.exp_event_rate(now)));
+
+        user.voice_time = user.voice_time.saturating_add(minutes);
+        let mut level = game::Level::new(user.exp);
+        let result = level.add(rate.apply(game::VoiceTime { minutes }.calculate(level.level)));
+
+        user.exp = level.exp;
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(id, &user)).await;
+
+        if result == game::LevelAddResult::LevelUp {
+            self.announce_level_up(ctx, server_id, None, UserId(id), level.level).await
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_floor_partial_minute_of_session() {
+        assert_eq!(session_minutes(time::Duration::from_secs(30)), 0);
+        assert_eq!(session_minutes(time::Duration::from_secs(59)), 0);
+        assert_eq!(session_minutes(time::Duration::from_secs(60)), 1);
+        assert_eq!(session_minutes(time::Duration::from_secs(119)), 1);
+        assert_eq!(session_minutes(time::Duration::from_secs(120)), 2);
+    }
+}
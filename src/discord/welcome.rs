@@ -0,0 +1,367 @@
+//! Welcome of new members
+
+use super::*;
+use super::args::{Args, ArgError};
+use super::context::Source;
+use crate::data::{Template, TemplateKind, WelcomeCard};
+
+use std::time;
+
+//Limits of uploaded background
+const MAX_BACKGROUND_SIZE: u32 = 4 * 1024 * 1024;
+const MAX_BACKGROUND_DIMENSION: u32 = 2048;
+const MIN_FONT_SIZE: u16 = 8;
+const MAX_FONT_SIZE: u16 = 128;
+//Card's text, unless server has its own
+const DEFAULT_CARD_TEXT: &str = "{name}";
+//Size of downloaded avatar in pixels, discord only allows powers of 2
+const AVATAR_SIZE: u16 = 256;
+const AVATAR_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+//Discord's limit on message length
+const MAX_MESSAGE_LEN: usize = 2000;
+
+///Returns PNG link of user's own avatar, discord's default is WEBP.
+fn avatar_url(user: &User) -> Option<String> {
+    user.avatar.as_ref().map(|hash| format!("https://cdn.discordapp.com/avatars/{}/{}.png?size={}", user.id.0, hash, AVATAR_SIZE))
+}
+
+async fn download(url: &str) -> Result<Vec<u8>, reqwest::Error> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    response.bytes().await.map(|bytes| bytes.to_vec())
+}
+
+///Downloads user's avatar, falling back to default one.
+pub(super) async fn download_avatar(user: &User) -> Option<Vec<u8>> {
+    let urls = avatar_url(user).into_iter().chain(core::iter::once(user.default_avatar_url()));
+
+    for url in urls {
+        match tokio::time::timeout(AVATAR_TIMEOUT, download(&url)).await {
+            Ok(Ok(avatar)) => return Some(avatar),
+            Ok(Err(error)) => rogu::warn!("Unable to download avatar {}: {}", url, error),
+            Err(_) => rogu::warn!("Unable to download avatar {}: timed out", url),
+        }
+    }
+
+    None
+}
+
+pub(super) fn encode_png(img: &image::DynamicImage) -> Option<Vec<u8>> {
+    let mut buffer = Vec::new();
+    match img.write_to(&mut buffer, image::ImageOutputFormat::Png) {
+        Ok(()) => Some(buffer),
+        Err(error) => {
+            rogu::error!("Unexpected error generating image: {}", error);
+            None
+        }
+    }
+}
+
+///Rendered welcome or goodbye of member.
+pub(super) struct Welcome {
+    pub text: String,
+    //PNG image
+    pub card: Option<Vec<u8>>,
+}
+
+impl Handler {
+    pub(super) fn welcome_template(&self, server: GuildId, kind: TemplateKind) -> Template {
+        match self.state.db.get::<Template>((server.0, kind as u64)) {
+            Ok(template) => template,
+            Err(error) => {
+                rogu::error!("Unable to get welcome template: {}", error);
+                Template::default()
+            }
+        }
+    }
+
+    ///Renders welcome of user, without card during raid.
+    async fn render_welcome(&self, ctx: &Context, server: GuildId, lang: Lang, user: &User, is_raid: bool) -> Welcome {
+        let layout = self.state.db.get::<WelcomeCard>(server.0).unwrap_or_default();
+        let avatar = match is_raid || layout.avatar_size == 0 {
+            true => None,
+            false => download_avatar(user).await,
+        };
+        let server_name = server.name(&ctx.cache).await.unwrap_or_default();
+
+        let mention = user.mention();
+        let render = |template: &str| {
+            let vars: &[(&str, &dyn fmt::Display)] = &[("mention", &mention), ("name", &user.name), ("server", &server_name)];
+            crate::utils::render_template(template, vars)
+        };
+
+        let template = self.welcome_template(server, TemplateKind::Welcome);
+        //Raid would flood channel with images
+        let card = match is_raid {
+            true => None,
+            false => {
+                let card_text = self.welcome_template(server, TemplateKind::WelcomeCard);
+                let card_text = render(card_text.get().unwrap_or(DEFAULT_CARD_TEXT));
+                let background = self.state.db.background(server.0);
+                let assets = self.state.assets.clone();
+
+                //Decoding and drawing would stall runtime
+                tokio::task::spawn_blocking(move || {
+                    let background = background.and_then(|background| match image::load_from_memory(&background) {
+                        Ok(background) => Some(background),
+                        Err(error) => {
+                            rogu::warn!("Unable to load background of server {}: {}", server.0, error);
+                            None
+                        }
+                    });
+                    assets.gen_welcome(&card_text, &layout, background.as_ref(), avatar.as_deref()).as_ref().and_then(encode_png)
+                }).await.unwrap_or(None)
+            }
+        };
+
+        let text = match (template.get(), &card) {
+            (Some(template), _) => render(template),
+            //Card greets by itself
+            (None, Some(_)) => mention.to_string(),
+            (None, None) => render(Msg::Welcome.get(lang)),
+        };
+
+        Welcome {
+            text,
+            card,
+        }
+    }
+
+    ///Renders onboarding DM of user, if server has one.
+    async fn render_welcome_dm(&self, ctx: &Context, server: GuildId, server_info: &data::Server, user: &User) -> Option<String> {
+        let (server_name, rules) = match ctx.cache.guild(server).await {
+            Some(guild) => (guild.name, guild.rules_channel_id.map(|channel| channel.mention().to_string()).unwrap_or_default()),
+            None => (String::new(), String::new()),
+        };
+
+        let template = self.welcome_template(server, TemplateKind::WelcomeDm);
+        let template = template.get()?;
+
+        let mention = user.mention();
+        let prefix = server_info.prefix().unwrap_or(self.config.prefix.as_str());
+        let vars: &[(&str, &dyn fmt::Display)] = &[("mention", &mention), ("name", &user.name), ("server", &server_name), ("prefix", &prefix), ("rules", &rules)];
+
+        Some(crate::utils::render_template(template, vars))
+    }
+
+    ///Sends onboarding DM to new member, posting it in welcome channel if member has DMs closed.
+    async fn send_welcome_dm(&self, ctx: &Context, server: GuildId, server_info: &data::Server, user: &User) {
+        let text = match self.render_welcome_dm(ctx, server, server_info, user).await {
+            Some(text) => text,
+            None => return,
+        };
+
+        let error = match user.direct_message(ctx, |msg| msg.content(crate::utils::truncate(&text, MAX_MESSAGE_LEN))).await {
+            Ok(_) => return,
+            Err(error) => error,
+        };
+        rogu::info!("Unable to DM welcome to user {}: {}", user.id.0, error);
+
+        if server_info.welcome_ch == 0 {
+            return;
+        }
+
+        let header = crate::utils::render_template(Msg::DmClosed.get(server_info.lang), &[("mention", &user.mention())]);
+        let text = format!("{}\n{}", header, text);
+        if let Err(error) = ChannelId(server_info.welcome_ch).say(&ctx.http, crate::utils::truncate(&text, MAX_MESSAGE_LEN)).await {
+            rogu::error!("Unable to post welcome DM: {}", error);
+        }
+    }
+
+    pub(super) async fn send_welcome(ctx: &Context, channel: ChannelId, welcome: Welcome, filename: &str) -> serenity::Result<()> {
+        match welcome.card {
+            Some(card) => {
+                let attach = serenity::http::AttachmentType::Bytes {
+                    data: card.into(),
+                    filename: filename.to_owned(),
+                };
+
+                channel.send_files(&ctx.http, Some(attach), |msg| msg.content(welcome.text)).await.map(|_| ())
+            },
+            None => channel.say(&ctx.http, welcome.text).await.map(|_| ()),
+        }
+    }
+
+    ///Welcomes new member publicly and privately, without image during raid.
+    pub(super) async fn welcome_member(&self, ctx: &Context, server: GuildId, user: &User, is_raid: bool) {
+        let server_info: data::Server = match self.state.db.get(server.0) {
+            Ok(server_info) => server_info,
+            Err(error) => {
+                rogu::error!("Unable to get server info: {}", error);
+                return;
+            }
+        };
+
+        //Raiders aren't onboarded
+        if !is_raid {
+            self.send_welcome_dm(ctx, server, &server_info, user).await;
+        }
+
+        if server_info.welcome_ch == 0 {
+            return;
+        }
+
+        let welcome = self.render_welcome(ctx, server, server_info.lang, user, is_raid).await;
+        if let Err(error) = Self::send_welcome(ctx, ChannelId(server_info.welcome_ch), welcome, "welcome.png").await {
+            rogu::error!("Unable to post welcome: {}", error);
+        }
+    }
+
+    #[inline]
+    pub async fn handle_welcome(&self, ctx: HandlerContext<'_>) -> serenity::Result<()> {
+        let mut args = Args::new(ctx.args);
+
+        let server = match ctx.guild_id {
+            Some(server) => server,
+            None => {
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            },
+        };
+
+        let mut layout = match self.state.db.get::<WelcomeCard>(server.0) {
+            Ok(layout) => layout,
+            Err(error) => {
+                rogu::error!("Unable to get welcome card: {}", error);
+                let _ = ctx.react(emoji::KINSHI).await;
+                return Ok(());
+            }
+        };
+
+        match args.word() {
+            None => {
+                let [red, green, blue] = layout.color;
+                let card_text = self.welcome_template(server, TemplateKind::WelcomeCard);
+                let message = self.welcome_template(server, TemplateKind::Welcome);
+                let dm = self.welcome_template(server, TemplateKind::WelcomeDm);
+                let background = match self.state.db.background(server.0) {
+                    Some(_) => "Custom",
+                    None => "Default",
+                };
+                let avatar = match layout.avatar_size {
+                    0 => "Off".to_owned(),
+                    size => format!("x: {}, y: {}, size: {}", layout.avatar_x, layout.avatar_y, size),
+                };
+
+                ctx.reply_embed_private(|m| {
+                    m.title("Welcome card")
+                     .field("Background", background, false)
+                     .field("Text position", format_args!("x: {}, y: {}, width: {}", layout.x, layout.y, layout.width), false)
+                     .field("Font size", layout.font_size, false)
+                     .field("Avatar", avatar, false)
+                     .field("Colour", format_args!("#{:02x}{:02x}{:02x}", red, green, blue), false)
+                     .field("Card text", card_text.get().unwrap_or(DEFAULT_CARD_TEXT), false)
+                     .field("Message", message.get().unwrap_or("Default"), false)
+                     .field("DM", dm.get().unwrap_or("Off"), false)
+                }).await?;
+                return ctx.react(emoji::OK).await;
+            },
+            Some("preview") => {
+                let lang = ctx.lang;
+                let welcome = self.render_welcome(ctx.serenity, server, lang, ctx.author, false).await;
+                Self::send_welcome(ctx.serenity, ctx.channel_id, welcome, "welcome.png").await?;
+                if let Ok(server_info) = self.state.db.get::<data::Server>(server.0) {
+                    self.send_welcome_dm(ctx.serenity, server, &server_info, ctx.author).await;
+                }
+                return ctx.react(emoji::OK).await;
+            },
+            Some("position") => {
+                layout.x = try_arg!(ctx, args.next::<u16>("x"));
+                layout.y = try_arg!(ctx, args.next::<u16>("y"));
+                if let Some(width) = try_arg!(ctx, args.optional::<u16>("width")) {
+                    layout.width = width;
+                }
+            },
+            Some("avatar") => match try_arg!(ctx, args.next::<&str>("x")) {
+                "off" => layout.avatar_size = 0,
+                x => {
+                    layout.avatar_x = match x.parse() {
+                        Ok(x) => x,
+                        Err(_) => return ctx.reply_usage(ArgError::Invalid("x")).await,
+                    };
+                    layout.avatar_y = try_arg!(ctx, args.next::<u16>("y"));
+                    layout.avatar_size = match try_arg!(ctx, args.next::<u16>("size")) {
+                        size if size > 0 && u32::from(size) <= MAX_BACKGROUND_DIMENSION => size,
+                        _ => return ctx.reply_usage(ArgError::Invalid("size")).await,
+                    };
+                },
+            },
+            Some("size") => match try_arg!(ctx, args.next::<u16>("size")) {
+                size if (MIN_FONT_SIZE..=MAX_FONT_SIZE).contains(&size) => layout.font_size = size,
+                _ => return ctx.reply(ctx.tr_with(Msg::BadFontSize, &[("min", &MIN_FONT_SIZE), ("max", &MAX_FONT_SIZE)])).await,
+            },
+            Some("color") | Some("colour") => match crate::utils::parse_color(try_arg!(ctx, args.next::<&str>("color"))) {
+                Some(color) => layout.color = color,
+                None => return ctx.reply_usage(ArgError::Invalid("color")).await,
+            },
+            Some(kind @ "text") | Some(kind @ "message") | Some(kind @ "dm") => {
+                let template_id = match kind {
+                    "text" => (server.0, TemplateKind::WelcomeCard as u64),
+                    "dm" => (server.0, TemplateKind::WelcomeDm as u64),
+                    _ => (server.0, TemplateKind::Welcome as u64),
+                };
+
+                let db = self.state.db.clone();
+                match args.rest() {
+                    "" if kind == "dm" => return ctx.reply(ctx.tr(Msg::WelcomeDmPlaceholders)).await,
+                    "" => return ctx.reply(ctx.tr(Msg::WelcomePlaceholders)).await,
+                    "reset" => {
+                        let _ = tokio::task::spawn_blocking(move || db.delete::<Template>(template_id)).await;
+                    },
+                    template => {
+                        let template = Template::new(template);
+                        let _ = tokio::task::spawn_blocking(move || db.put(template_id, &template)).await;
+                    },
+                }
+                return ctx.react(emoji::OK).await;
+            },
+            Some("background") => {
+                if args.word() == Some("reset") {
+                    let db = self.state.db.clone();
+                    let _ = tokio::task::spawn_blocking(move || db.delete_background(server.0)).await;
+                    return ctx.react(emoji::OK).await;
+                }
+
+                //Slash commands cannot carry files
+                let attachment = match ctx.source {
+                    Source::Message(msg) => msg.attachments.first(),
+                    Source::Interaction(..) => None,
+                };
+                let attachment = match attachment {
+                    Some(attachment) if attachment.size <= u64::from(MAX_BACKGROUND_SIZE) => attachment,
+                    Some(_) => return ctx.reply(ctx.tr_with(Msg::BadBackground, &[("max", &MAX_BACKGROUND_DIMENSION), ("size", &(MAX_BACKGROUND_SIZE / 1024 / 1024))])).await,
+                    None => return ctx.reply(ctx.tr(Msg::NoAttachment)).await,
+                };
+
+                let data = match attachment.download().await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        rogu::warn!("Unable to download background: {}", error);
+                        return ctx.reply(ctx.tr(Msg::DownloadFailed)).await;
+                    }
+                };
+                //Header is enough to reject huge images without decoding them
+                let dimensions = image::io::Reader::new(std::io::Cursor::new(&data)).with_guessed_format().ok().and_then(|reader| reader.into_dimensions().ok());
+                let data = match dimensions {
+                    Some((width, height)) if width <= MAX_BACKGROUND_DIMENSION && height <= MAX_BACKGROUND_DIMENSION => {
+                        tokio::task::spawn_blocking(move || image::load_from_memory(&data).map(|_| data)).await.ok().and_then(Result::ok)
+                    },
+                    _ => None,
+                };
+                let data = match data {
+                    Some(data) => data,
+                    None => return ctx.reply(ctx.tr_with(Msg::BadBackground, &[("max", &MAX_BACKGROUND_DIMENSION), ("size", &(MAX_BACKGROUND_SIZE / 1024 / 1024))])).await,
+                };
+
+                let db = self.state.db.clone();
+                let _ = tokio::task::spawn_blocking(move || db.put_background(server.0, &data)).await;
+                return ctx.react(emoji::OK).await;
+            },
+            Some(_) => return ctx.reply_usage(ArgError::Invalid("setting")).await,
+        }
+
+        let db = self.state.db.clone();
+        let _ = tokio::task::spawn_blocking(move || db.put(server.0, &layout)).await;
+
+        ctx.react(emoji::OK).await
+    }
+}
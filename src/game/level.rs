@@ -16,6 +16,33 @@ pub trait LevelExpModifier {
     fn calculate(&self, level: u8) -> u32;
 }
 
+///Rate in percents, applied on top of calculated experience.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExpRate(pub u16);
+
+impl ExpRate {
+    pub const NORMAL: Self = ExpRate(100);
+
+    ///Combines with other rate, e.g. `150%` of `200%` is `300%`
+    pub const fn compose(self, other: Self) -> Self {
+        let result = self.0 as u32 * other.0 as u32 / 100;
+        if result > u16::max_value() as u32 {
+            ExpRate(u16::max_value())
+        } else {
+            ExpRate(result as u16)
+        }
+    }
+
+    pub const fn apply(self, exp: u32) -> u32 {
+        let result = exp as u64 * self.0 as u64 / 100;
+        if result > u32::max_value() as u64 {
+            u32::max_value()
+        } else {
+            result as u32
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum AddResult {
     Maxed,
@@ -123,6 +150,22 @@ mod tests {
         println!("{}", level);
     }
 
+    #[test]
+    fn should_apply_exp_rate() {
+        let double = ExpRate(200);
+        let half = ExpRate(50);
+        let none = ExpRate(0);
+
+        assert_eq!(ExpRate::NORMAL.apply(15), 15);
+        assert_eq!(double.apply(15), 30);
+        assert_eq!(half.apply(15), 7);
+        assert_eq!(none.apply(15), 0);
+        assert_eq!(double.compose(ExpRate(150)), ExpRate(300));
+        assert_eq!(double.compose(half), ExpRate::NORMAL);
+        assert_eq!(double.compose(none).apply(15), 0);
+        assert_eq!(ExpRate(u16::max_value()).compose(double), ExpRate(u16::max_value()));
+    }
+
     #[test]
     fn verify_level() {
         assert_eq!(Level::exp_to_level(MAX_EXP), 99);
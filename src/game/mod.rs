@@ -1,2 +1,4 @@
 mod level;
-pub use level::{AddResult as LevelAddResult, Level, LevelExpModifier};
+pub use level::{AddResult as LevelAddResult, ExpRate, Level, LevelExpModifier};
+mod voice;
+pub use voice::VoiceTime;
@@ -0,0 +1,35 @@
+use super::LevelExpModifier;
+
+///Time spent talking in voice channel.
+pub struct VoiceTime {
+    pub minutes: u32,
+}
+
+impl LevelExpModifier for VoiceTime {
+    fn calculate(&self, level: u8) -> u32 {
+        //Sitting in voice requires less effort than typing,
+        //so it is roughly equal to the shortest message per minute.
+        if level == 0 {
+            self.minutes
+        } else {
+            self.minutes.saturating_mul(level as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Level, LevelAddResult};
+
+    #[test]
+    fn should_add_voice_exp_per_minute() {
+        let mut level = Level::new(0);
+        assert_eq!(level.add_for(&VoiceTime { minutes: 54 }), LevelAddResult::Added);
+        assert_eq!(level.exp, 54);
+        assert_eq!(level.add_for(&VoiceTime { minutes: 1 }), LevelAddResult::LevelUp);
+        assert_eq!(level.level, 1);
+        assert_eq!(level.add_for(&VoiceTime { minutes: 10 }), LevelAddResult::Added);
+        assert_eq!(level.exp, 65);
+    }
+}
@@ -0,0 +1,374 @@
+//! Localized messages
+//!
+//! Messages may contain `{name}` placeholders, which are filled by `utils::render_template`.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+///Language of bot's responses.
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    pub const ALL: [Lang; 2] = [Lang::En, Lang::Ja];
+
+    #[inline]
+    pub const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Lang::Ja,
+            _ => Lang::En,
+        }
+    }
+
+    #[inline]
+    pub const fn to_u8(self) -> u8 {
+        match self {
+            Lang::En => 0,
+            Lang::Ja => 1,
+        }
+    }
+
+    #[inline]
+    ///Returns ISO 639-1 code.
+    pub const fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Ja => "ja",
+        }
+    }
+
+    #[inline]
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lang| lang.code().eq_ignore_ascii_case(code))
+    }
+}
+
+impl Default for Lang {
+    #[inline]
+    fn default() -> Self {
+        Lang::En
+    }
+}
+
+macro_rules! messages {
+    ($($name:ident,)+) => {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        ///Key of localized message.
+        pub enum Msg {
+            $($name,)+
+        }
+
+        impl Msg {
+            pub const ALL: &'static [Msg] = &[$(Msg::$name,)+];
+        }
+    }
+}
+
+messages!(
+    Pong,
+    Roll,
+    BadDice,
+    NeedChoices,
+    Judge,
+    NoUserInfo,
+    BrokenTime,
+    NoWallet,
+    Allowance,
+    MusicNoChannel,
+    MusicNoCash,
+    MusicBadLink,
+    PlayerUnknown,
+    NoDevChannel,
+    SuggestNoCash,
+    SuggestFailed,
+    NotInVoice,
+    UnknownCommand,
+    DidYouMean,
+    RateLimited,
+    ArgMissing,
+    ArgInvalid,
+    ArgUsage,
+    LevelUp,
+    Welcome,
+    Goodbye,
+    BadRate,
+    BadWeight,
+    ExpWeight,
+    LevelUpPlaceholders,
+    PrefixTooLong,
+    CurrentPrefix,
+    RoleNotFound,
+    RoleTooHigh,
+    TooManyModRoles,
+    NotModRole,
+    NoPermRules,
+    CommandLocked,
+    ModAllowTooBroad,
+    NoSuchCommand,
+    TargetNotFound,
+    CurrentLang,
+    CaseOpened,
+    CaseNotFound,
+    NoCases,
+    NoReason,
+    CannotModerate,
+    ActionFailed,
+    NoMuteRole,
+    Warned,
+    AutoModNotice,
+    BlocklistEmpty,
+    PatternNotFound,
+    PatternTooLong,
+    BadRegex,
+    TooManyExemptions,
+    RaidDetected,
+    RaidJoinRate,
+    RaidAccountAge,
+    Purged,
+    AlreadyLocked,
+    NotLocked,
+    LockFailed,
+    LockedDown,
+    LockdownLifted,
+    NoLockdownChannels,
+    BadSlowmode,
+    MessageNotFound,
+    ReactionRoleNotFound,
+    NoReactionRoles,
+    TooManyReactionRoles,
+    TooManyAutoRoles,
+    WrongAnswer,
+    GateReact,
+    GateQuestion,
+    QuestionTooLong,
+    GatePostFailed,
+    WelcomePlaceholders,
+    WelcomeDmPlaceholders,
+    DmClosed,
+    BadFontSize,
+    NoAttachment,
+    DownloadFailed,
+    BadBackground,
+);
+
+impl Msg {
+    #[inline]
+    ///Returns message in specified language, falling back to English if it is not translated.
+    pub fn get(self, lang: Lang) -> &'static str {
+        match lang {
+            Lang::En => en(self),
+            Lang::Ja => ja(self).unwrap_or_else(|| en(self)),
+        }
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Pong => "pong!",
+        Msg::Roll => "Roll {dice}: {result}",
+        Msg::BadDice => "Cannot recognize dice: {error}",
+        Msg::NeedChoices => "You need to give me at least two choices.",
+        Msg::Judge => "The criminal is {choice}",
+        Msg::NoUserInfo => "Cannot find your dossier :(",
+        Msg::BrokenTime => "My watch is broken, cannot do it now",
+        Msg::NoWallet => "Cannot access your wallet :(",
+        Msg::Allowance => "Your allowance is {amount}¥",
+        Msg::MusicNoChannel => "Voice channel is not set yet, please do so.",
+        Msg::MusicNoCash => "You do not have enough cash({cost}¥) for music :P",
+        Msg::MusicBadLink => "Cannot download it, is this a youtube link?",
+        Msg::PlayerUnknown => "Unknown command, allowed: start, stop",
+        Msg::NoDevChannel => "Dev channel is not set yet, please ask mods.",
+        Msg::SuggestNoCash => "You do not have enough cash({cost}¥) to post suggestion",
+        Msg::SuggestFailed => "I'm sorry I cannot post your suggestion :(",
+        Msg::NotInVoice => "You're not in any channel, please join one before using the command",
+        Msg::UnknownCommand => "Sorry, I do not know such command",
+        Msg::DidYouMean => ". Did you mean `{command}`?",
+        Msg::RateLimited => "Slow down, you can use `{command}` again in {secs}s",
+        Msg::ArgMissing => "Missing {name}",
+        Msg::ArgInvalid => "Invalid {name}",
+        Msg::ArgUsage => "{error}. Usage: `{usage}`",
+        Msg::LevelUp => "{mention} Congratulations on level up! Your new level is {level}",
+        Msg::Welcome => "{mention}: Welcome to the server!",
+        Msg::Goodbye => "{name} has left the server. Goodbye!",
+        Msg::BadRate => "Rate should be a multiplier between 0 and 10, e.g. `1.5`",
+        Msg::BadWeight => "Weight should be a multiplier between 0 and 10, e.g. `1.5`",
+        Msg::ExpWeight => "Exp weight of this channel is {weight}%",
+        Msg::LevelUpPlaceholders => "Message can use placeholders: {mention}, {level}, {rank}",
+        Msg::PrefixTooLong => "Prefix cannot be longer than {max} bytes",
+        Msg::CurrentPrefix => "Current prefix is `{prefix}`, you can also mention me instead",
+        Msg::RoleNotFound => "Cannot find such role",
+        Msg::RoleTooHigh => "I can only give roles below both your and my highest role, which aren't managed by integrations",
+        Msg::TooManyModRoles => "Cannot have more than {max} moderator roles",
+        Msg::NotModRole => "This role is not moderator role",
+        Msg::NoPermRules => "No rules, moderator's commands are available to moderators only.",
+        Msg::CommandLocked => "This command cannot be changed",
+        Msg::ModAllowTooBroad => "Moderation commands can only be allowed to roles or users",
+        Msg::NoSuchCommand => "I do not know such command",
+        Msg::TargetNotFound => "Cannot find such role, channel or user",
+        Msg::CurrentLang => "Current language is `{lang}`. Available: {available}",
+        Msg::CaseOpened => "Case #{case}: {action} {user}",
+        Msg::CaseNotFound => "Cannot find case #{case}",
+        Msg::NoCases => "{user} has no cases",
+        Msg::NoReason => "No reason given",
+        Msg::CannotModerate => "I cannot do it to this user",
+        Msg::ActionFailed => "I failed to {action} this user, do I have permissions?",
+        Msg::NoMuteRole => "Mute role is not set, please set it with `muterole`",
+        Msg::Warned => "You have been warned on {server}: {reason}",
+        Msg::AutoModNotice => "{user}, your message has been removed by `{filter}` filter",
+        Msg::BlocklistEmpty => "Blocklist is empty",
+        Msg::PatternNotFound => "Cannot find such pattern in blocklist",
+        Msg::PatternTooLong => "Pattern cannot be longer than {max} bytes",
+        Msg::BadRegex => "Invalid regex: {error}",
+        Msg::TooManyExemptions => "Cannot have more than {max} exempted channels or roles",
+        Msg::RaidDetected => "{mods}Raid mode is on, because {reason}. New members are handled by `{action}` action until `raid lift`",
+        Msg::RaidJoinRate => "{joins} members joined within {secs}s",
+        Msg::RaidAccountAge => "member with {days} days old account joined",
+        Msg::Purged => "Removed {count} messages",
+        Msg::AlreadyLocked => "Channel is already locked",
+        Msg::NotLocked => "Channel is not locked",
+        Msg::LockFailed => "Unable to change channel, check bot's permissions",
+        Msg::LockedDown => "Locked {count} channels",
+        Msg::LockdownLifted => "Unlocked {count} channels",
+        Msg::NoLockdownChannels => "No lockdown channels, add them with `lockdown add <channel>`",
+        Msg::BadSlowmode => "Slowmode cannot be longer than {max}",
+        Msg::MessageNotFound => "Cannot find such message",
+        Msg::ReactionRoleNotFound => "No reaction role is bound to it",
+        Msg::NoReactionRoles => "No reaction roles, add them with `reactrole add <message> <emoji> <role>`",
+        Msg::TooManyReactionRoles => "Message cannot have more than {max} reaction roles",
+        Msg::TooManyAutoRoles => "Cannot have more than {max} auto-roles",
+        Msg::WrongAnswer => "Wrong answer, try again",
+        Msg::GateReact => "Welcome! React with {emoji} to get access to the server",
+        Msg::GateQuestion => "Welcome! Answer the question with `verify <answer>` to get access to the server:\n{question}",
+        Msg::QuestionTooLong => "Question cannot be longer than {question} bytes, and answer than {answer} bytes",
+        Msg::GatePostFailed => "I failed to post gate message, can I write in that channel?",
+        Msg::WelcomePlaceholders => "Message can use placeholders: {mention}, {name}, {server}. Use `reset` to restore default",
+        Msg::WelcomeDmPlaceholders => "DM can use placeholders: {mention}, {name}, {server}, {prefix}, {rules}. Use `reset` to stop sending it",
+        Msg::DmClosed => "{mention} I couldn't send you a DM, so here it is:",
+        Msg::BadFontSize => "Font size must be between {min} and {max}",
+        Msg::NoAttachment => "Attach image to the command's message",
+        Msg::DownloadFailed => "I failed to download the attachment, try again later",
+        Msg::BadBackground => "Background must be PNG or JPEG image up to {max}x{max} pixels and {size}MB",
+    }
+}
+
+#[allow(unreachable_patterns)]
+fn ja(msg: Msg) -> Option<&'static str> {
+    let text = match msg {
+        Msg::Pong => "ぽん！",
+        Msg::Roll => "{dice}を振った結果: {result}",
+        Msg::BadDice => "ダイスが分かりません: {error}",
+        Msg::NeedChoices => "選択肢を二つ以上ください。",
+        Msg::Judge => "犯人は{choice}です",
+        Msg::NoUserInfo => "あなたの資料が見つかりません :(",
+        Msg::BrokenTime => "時計が壊れているので、今はできません",
+        Msg::NoWallet => "財布が開けません :(",
+        Msg::Allowance => "お小遣いは{amount}¥です",
+        Msg::MusicNoChannel => "ボイスチャンネルがまだ設定されていません。",
+        Msg::MusicNoCash => "音楽にはお金({cost}¥)が足りません :P",
+        Msg::MusicBadLink => "ダウンロードできません。YouTubeのリンクですか？",
+        Msg::PlayerUnknown => "不明なコマンドです。使えるのは: start, stop",
+        Msg::NoDevChannel => "開発チャンネルがまだ設定されていません。モデレーターに聞いてください。",
+        Msg::SuggestNoCash => "提案を送るにはお金({cost}¥)が足りません",
+        Msg::SuggestFailed => "すみません、提案を送れませんでした :(",
+        Msg::NotInVoice => "ボイスチャンネルに入っていません。参加してからもう一度どうぞ",
+        Msg::UnknownCommand => "すみません、そのコマンドは知りません",
+        Msg::DidYouMean => "。`{command}`のことですか？",
+        Msg::RateLimited => "少し待ってください。`{command}`は{secs}秒後に使えます",
+        Msg::ArgMissing => "{name}がありません",
+        Msg::ArgInvalid => "{name}が正しくありません",
+        Msg::ArgUsage => "{error}。使い方: `{usage}`",
+        Msg::LevelUp => "{mention} レベルアップおめでとう！新しいレベルは{level}です",
+        Msg::Welcome => "{mention}: サーバーへようこそ！",
+        Msg::Goodbye => "{name}がサーバーを去りました。さようなら！",
+        Msg::BadRate => "倍率は0から10までの数字にしてください。例: `1.5`",
+        Msg::BadWeight => "重みは0から10までの数字にしてください。例: `1.5`",
+        Msg::ExpWeight => "このチャンネルの経験値の重みは{weight}%です",
+        Msg::LevelUpPlaceholders => "メッセージに使えるプレースホルダー: {mention}, {level}, {rank}",
+        Msg::PrefixTooLong => "プレフィックスは{max}バイト以内にしてください",
+        Msg::CurrentPrefix => "現在のプレフィックスは`{prefix}`です。メンションでも呼べます",
+        Msg::RoleNotFound => "そのロールが見つかりません",
+        Msg::RoleTooHigh => "あなたと私の最上位ロールより下で、連携に管理されていないロールしか付与できません",
+        Msg::TooManyModRoles => "モデレーターロールは{max}個までです",
+        Msg::NotModRole => "このロールはモデレーターロールではありません",
+        Msg::NoPermRules => "ルールはありません。モデレーター用コマンドはモデレーターだけが使えます。",
+        Msg::CommandLocked => "このコマンドは変更できません",
+        Msg::ModAllowTooBroad => "モデレーションコマンドはロールかユーザーにしか許可できません",
+        Msg::NoSuchCommand => "そのコマンドは知りません",
+        Msg::TargetNotFound => "そのロール、チャンネル、ユーザーが見つかりません",
+        Msg::CurrentLang => "現在の言語は`{lang}`です。使える言語: {available}",
+        Msg::CaseOpened => "ケース#{case}: {user}に{action}",
+        Msg::CaseNotFound => "ケース#{case}が見つかりません",
+        Msg::NoCases => "{user}のケースはありません",
+        Msg::NoReason => "理由なし",
+        Msg::CannotModerate => "このユーザーにはできません",
+        Msg::ActionFailed => "このユーザーに{action}できませんでした。権限はありますか？",
+        Msg::NoMuteRole => "ミュートロールが設定されていません。`muterole`で設定してください",
+        Msg::Warned => "{server}で警告を受けました: {reason}",
+        Msg::AutoModNotice => "{user}さんのメッセージは`{filter}`フィルターで削除されました",
+        Msg::BlocklistEmpty => "ブロックリストは空です",
+        Msg::PatternNotFound => "そのパターンはブロックリストにありません",
+        Msg::PatternTooLong => "パターンは{max}バイト以内にしてください",
+        Msg::BadRegex => "正規表現が正しくありません: {error}",
+        Msg::TooManyExemptions => "除外できるチャンネルやロールは{max}個までです",
+        Msg::RaidDetected => "{mods}{reason}ため、レイドモードになりました。`raid lift`まで新しいメンバーには`{action}`が適用されます",
+        Msg::RaidJoinRate => "{secs}秒以内に{joins}人が参加した",
+        Msg::RaidAccountAge => "作成から{days}日のアカウントが参加した",
+        Msg::Purged => "{count}件のメッセージを削除しました",
+        Msg::AlreadyLocked => "このチャンネルは既にロックされています",
+        Msg::NotLocked => "このチャンネルはロックされていません",
+        Msg::LockFailed => "チャンネルを変更できません。ボットの権限を確認してください",
+        Msg::LockedDown => "{count}個のチャンネルをロックしました",
+        Msg::LockdownLifted => "{count}個のチャンネルのロックを解除しました",
+        Msg::NoLockdownChannels => "ロックダウン対象のチャンネルがありません。`lockdown add <channel>`で追加してください",
+        Msg::BadSlowmode => "低速モードは{max}以内にしてください",
+        Msg::MessageNotFound => "そのメッセージが見つかりません",
+        Msg::ReactionRoleNotFound => "リアクションロールが設定されていません",
+        Msg::NoReactionRoles => "リアクションロールがありません。`reactrole add <message> <emoji> <role>`で追加してください",
+        Msg::TooManyReactionRoles => "リアクションロールは1つのメッセージに{max}個までです",
+        Msg::TooManyAutoRoles => "自動ロールは{max}個までです",
+        Msg::WrongAnswer => "答えが違います。もう一度お試しください",
+        Msg::GateReact => "ようこそ！{emoji}でリアクションするとサーバーに参加できます",
+        Msg::GateQuestion => "ようこそ！`verify <答え>`で質問に答えるとサーバーに参加できます:\n{question}",
+        Msg::QuestionTooLong => "質問は{question}バイト、答えは{answer}バイト以内にしてください",
+        Msg::GatePostFailed => "ゲートのメッセージを投稿できませんでした。そのチャンネルに書き込めますか？",
+        Msg::WelcomePlaceholders => "メッセージに使えるプレースホルダー: {mention}, {name}, {server}。`reset`でデフォルトに戻します",
+        Msg::WelcomeDmPlaceholders => "DMに使えるプレースホルダー: {mention}, {name}, {server}, {prefix}, {rules}。`reset`で送信をやめます",
+        Msg::DmClosed => "{mention} DMを送れなかったので、ここに載せます:",
+        Msg::BadFontSize => "フォントサイズは{min}から{max}の間にしてください",
+        Msg::NoAttachment => "コマンドのメッセージに画像を添付してください",
+        Msg::DownloadFailed => "添付ファイルをダウンロードできませんでした。後でもう一度お試しください",
+        Msg::BadBackground => "背景は{max}x{max}ピクセル、{size}MB以内のPNGかJPEG画像にしてください",
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut result: Vec<&str> = text.split('{').skip(1).filter_map(|part| part.split('}').next()).collect();
+        result.sort_unstable();
+        result
+    }
+
+    #[test]
+    fn verify_translations() {
+        for lang in Lang::ALL.iter().copied() {
+            assert_eq!(Lang::from_u8(lang.to_u8()), lang);
+            assert_eq!(Lang::from_code(lang.code()), Some(lang));
+
+            for msg in Msg::ALL.iter().copied() {
+                let text = match lang {
+                    Lang::En => Some(en(msg)),
+                    Lang::Ja => ja(msg),
+                };
+
+                let text = text.unwrap_or_else(|| panic!("{:?} is missing in {:?}", msg, lang));
+                assert_eq!(placeholders(text), placeholders(en(msg)), "{:?} has different placeholders in {:?}", msg, lang);
+            }
+        }
+    }
+}
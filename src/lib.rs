@@ -6,6 +6,7 @@ pub use core::sync::atomic::{AtomicBool, Ordering};
 pub mod utils;
 pub mod cli;
 pub mod data;
+pub mod lang;
 pub mod assets;
 pub mod db;
 pub mod game;
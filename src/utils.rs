@@ -76,3 +76,192 @@ impl<F: FnOnce() + Send + 'static, T: DropRunner> Drop for DropGuard<F, T> {
         }
     }
 }
+
+///Parses duration in format `1w2d3h4m5s`, where each unit is optional.
+///
+///Number without unit is treated as minutes.
+pub fn parse_duration(text: &str) -> Option<core::time::Duration> {
+    let mut result = 0u64;
+    let mut num = None;
+
+    for ch in text.chars() {
+        if let Some(digit) = ch.to_digit(10) {
+            num = Some(num.unwrap_or(0u64).checked_mul(10)?.checked_add(digit as u64)?);
+            continue;
+        }
+
+        let multiplier = match ch.to_ascii_lowercase() {
+            'w' => 7 * 24 * 60 * 60,
+            'd' => 24 * 60 * 60,
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+
+        result = result.checked_add(num.take()?.checked_mul(multiplier)?)?;
+    }
+
+    if let Some(minutes) = num {
+        result = result.checked_add(minutes.checked_mul(60)?)?;
+    } else if text.is_empty() {
+        return None;
+    }
+
+    Some(core::time::Duration::from_secs(result))
+}
+
+///Formats duration in the same format as `parse_duration`, omitting zero units.
+pub fn format_duration(duration: core::time::Duration) -> String {
+    use core::fmt::Write;
+
+    const UNITS: [(u64, char); 5] = [(7 * 24 * 60 * 60, 'w'), (24 * 60 * 60, 'd'), (60 * 60, 'h'), (60, 'm'), (1, 's')];
+
+    let mut secs = duration.as_secs();
+    let mut result = String::new();
+    for (unit, name) in UNITS.iter() {
+        if secs >= *unit {
+            let _ = write!(result, "{}{}", secs / unit, name);
+            secs %= unit;
+        }
+    }
+
+    if result.is_empty() {
+        result.push_str("0s");
+    }
+    result
+}
+
+///Parses rate, written as multiplier `1.5` or as percentage `150%`, into percents.
+pub fn parse_rate(text: &str) -> Option<u16> {
+    let result = match text.strip_suffix('%') {
+        Some(percent) => percent.parse::<f32>().ok()?,
+        None => text.parse::<f32>().ok()? * 100.0,
+    };
+
+    if (0.0..=u16::max_value() as f32).contains(&result) {
+        Some(result.round() as u16)
+    } else {
+        None
+    }
+}
+
+///Parses RGB colour, written as hex `#rrggbb`.
+pub fn parse_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let color = u32::from_str_radix(hex, 16).ok()?.to_be_bytes();
+    Some([color[1], color[2], color[3]])
+}
+
+///Truncates text to at most `max` bytes, keeping it valid UTF-8.
+pub fn truncate(text: &str, max: usize) -> &str {
+    let mut len = core::cmp::min(text.len(), max);
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    &text[..len]
+}
+
+///Renders template, substituting `{name}` placeholders with provided values.
+///
+///Unknown placeholders are left as it is.
+pub fn render_template(template: &str, vars: &[(&str, &dyn core::fmt::Display)]) -> String {
+    use core::fmt::Write;
+
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let var = rest[1..].find('}').map(|end| &rest[1..end + 1]);
+        match var.and_then(|var| vars.iter().find(|(name, _)| *name == var)) {
+            Some((name, value)) => {
+                let _ = write!(result, "{}", value);
+                rest = &rest[name.len() + 2..];
+            },
+            None => {
+                result.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+
+    #[test]
+    fn should_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("15"), Some(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("2D"), Some(Duration::from_secs(2 * 24 * 60 * 60)));
+        assert_eq!(parse_duration("1w1d"), Some(Duration::from_secs(8 * 24 * 60 * 60)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("1y"), None);
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+    }
+
+    #[test]
+    fn should_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(90 * 60)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(8 * 24 * 60 * 60 + 5)), "1w1d5s");
+
+        let duration = Duration::from_secs(2 * 24 * 60 * 60 + 3 * 60);
+        assert_eq!(parse_duration(&format_duration(duration)), Some(duration));
+    }
+
+    #[test]
+    fn should_parse_rate() {
+        assert_eq!(parse_rate("2"), Some(200));
+        assert_eq!(parse_rate("1.5"), Some(150));
+        assert_eq!(parse_rate("0"), Some(0));
+        assert_eq!(parse_rate("75%"), Some(75));
+        assert_eq!(parse_rate("-1"), None);
+        assert_eq!(parse_rate("abc"), None);
+        assert_eq!(parse_rate("1000"), None);
+    }
+
+    #[test]
+    fn should_parse_color() {
+        assert_eq!(parse_color("#eeb795"), Some([238, 183, 149]));
+        assert_eq!(parse_color("FF0000"), Some([255, 0, 0]));
+        assert_eq!(parse_color("#fff"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+        assert_eq!(parse_color("+12345"), None);
+    }
+
+    #[test]
+    fn should_truncate_at_char_boundary() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("すずみ", 4), "す");
+        assert_eq!(truncate("すずみ", 6), "すず");
+    }
+
+    #[test]
+    fn should_render_template() {
+        let vars: &[(&str, &dyn core::fmt::Display)] = &[("mention", &"<@1>"), ("level", &5)];
+
+        assert_eq!(render_template("{mention} reached level {level}!", vars), "<@1> reached level 5!");
+        assert_eq!(render_template("{level}{level}", vars), "55");
+        assert_eq!(render_template("{rank} {level", vars), "{rank} {level");
+        assert_eq!(render_template("{{level}}", vars), "{5}");
+        assert_eq!(render_template("レベル{level}", vars), "レベル5");
+        assert_eq!(render_template("", vars), "");
+    }
+}